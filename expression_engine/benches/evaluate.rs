@@ -0,0 +1,47 @@
+//!
+//! Tree-walking against compiled and batch evaluation of the same expression over a million
+//! inputs, run with `cargo bench --bench evaluate`.
+//!
+
+use expression_engine::parse;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const EVALUATIONS: usize = 1_000_000;
+const EXPRESSION: &str = "3x^2 - 2sin(x) + sqrt(abs(x)) / (1 + x^2)";
+
+/// Time `evaluate` over the inputs, returning the elapsed time and the sum of the results.
+fn time(mut evaluate: impl FnMut(f64) -> f64) -> (Duration, f64) {
+    let start = Instant::now();
+    let mut sum = 0.0;
+    for i in 0..EVALUATIONS {
+        sum += evaluate(black_box(i as f64 * 1e-3 - 500.0));
+    }
+    (start.elapsed(), sum)
+}
+
+fn main() {
+    let exp = parse(EXPRESSION).unwrap();
+    let compiled = exp.compile().unwrap();
+
+    let (tree, tree_sum) = time(|x| exp.evaluate("x", x).unwrap());
+    let (program, program_sum) = time(|x| compiled.eval(&[x]).unwrap());
+    assert_eq!(tree_sum, program_sum);
+
+    let xs: Vec<f64> = (0..EVALUATIONS).map(|i| i as f64 * 1e-3 - 500.0).collect();
+    let mut out = vec![0.0; EVALUATIONS];
+    let start = Instant::now();
+    exp.evaluate_many("x", black_box(&xs), &mut out).unwrap();
+    let batch = start.elapsed();
+    assert!((out.iter().sum::<f64>() - tree_sum).abs() <= tree_sum.abs() * 1e-12);
+
+    println!("{} evaluations of {}", EVALUATIONS, EXPRESSION);
+    println!("tree:     {:?}", tree);
+    println!("compiled: {:?}", program);
+    println!("batch:    {:?}", batch);
+    println!(
+        "speedup:  {:.1}x compiled, {:.1}x batch",
+        tree.as_secs_f64() / program.as_secs_f64(),
+        tree.as_secs_f64() / batch.as_secs_f64()
+    );
+}
@@ -0,0 +1,237 @@
+//!
+//! Queries about what an expression uses and how it is shaped, answered from the parsed tree
+//! without evaluating it.
+//!
+
+use crate::expr::{Expr, UnaryOperator};
+use crate::node::ExpressionNode;
+use crate::operator::Operator;
+use crate::visit::{walk_expr, Visitor};
+
+/// Collects names in order of first appearance, without repeats.
+#[derive(Default)]
+struct Names {
+    variables: Vec<String>,
+    functions: Vec<String>,
+}
+
+impl Visitor for Names {
+    fn visit_variable(&mut self, name: &str) {
+        if !self.variables.iter().any(|variable| variable == name) {
+            self.variables.push(name.to_string());
+        }
+    }
+
+    fn visit_call(&mut self, name: &str, args: &[Expr]) {
+        if !self.functions.iter().any(|function| function == name) {
+            self.functions.push(name.to_string());
+        }
+        for arg in args {
+            self.visit_expr(arg)
+        }
+    }
+}
+
+/// Counts the nodes and the longest path from the root to a leaf.
+#[derive(Default)]
+struct Shape {
+    nodes: usize,
+    level: usize,
+    depth: usize,
+}
+
+impl Visitor for Shape {
+    fn visit_expr(&mut self, expr: &Expr) {
+        self.nodes += 1;
+        self.level += 1;
+        self.depth = self.depth.max(self.level);
+        walk_expr(self, expr);
+        self.level -= 1;
+    }
+}
+
+impl Expr {
+    ///
+    /// The variables the expression reads, in order of first appearance.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use expression_engine::parse_expr;
+    ///
+    /// let expr = parse_expr("rate * t + sin(t) * pi").unwrap();
+    /// assert_eq!(expr.variables(), ["rate", "t"]);
+    /// assert_eq!(expr.functions(), ["sin"]);
+    /// ```
+    ///
+    pub fn variables(&self) -> Vec<String> {
+        self.names().variables
+    }
+
+    /// The functions the expression calls, in order of first appearance.
+    pub fn functions(&self) -> Vec<String> {
+        self.names().functions
+    }
+
+    /// Number of nodes on the longest path from the root to a leaf, 1 for a single operand.
+    pub fn depth(&self) -> usize {
+        self.shape().depth
+    }
+
+    /// Number of nodes in the tree.
+    pub fn node_count(&self) -> usize {
+        self.shape().nodes
+    }
+
+    /// Whether the expression reads no variables, so it evaluates to the same value every time.
+    pub fn is_constant(&self) -> bool {
+        self.variables().is_empty()
+    }
+
+    ///
+    /// Whether the expression is a polynomial in `variable`, treating every other variable as
+    /// a coefficient. Powers of `variable` need a constant non-negative integer exponent, and
+    /// `variable` must not appear in a divisor, a modulus or a function argument.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use expression_engine::parse_expr;
+    ///
+    /// assert!(parse_expr("3x^2 - a*x/2 + sin(a)").unwrap().is_polynomial_in("x"));
+    /// assert!(!parse_expr("1/x").unwrap().is_polynomial_in("x"));
+    /// assert!(!parse_expr("x^n").unwrap().is_polynomial_in("x"));
+    /// ```
+    ///
+    pub fn is_polynomial_in(&self, variable: &str) -> bool {
+        let free_of = |expr: &Expr| !expr.variables().iter().any(|name| name == variable);
+        match self {
+            Expr::Number { .. } | Expr::Constant { .. } | Expr::Variable(_) => true,
+            Expr::Unary {
+                op: UnaryOperator::Negate | UnaryOperator::Degrees,
+                operand,
+            } => operand.is_polynomial_in(variable),
+            Expr::Binary { op, left, right } => match op {
+                Operator::Add | Operator::Substract | Operator::Multiply => {
+                    left.is_polynomial_in(variable) && right.is_polynomial_in(variable)
+                }
+                Operator::Divide => left.is_polynomial_in(variable) && free_of(right),
+                Operator::Modulus => free_of(self),
+                Operator::Power => {
+                    free_of(self)
+                        || (left.is_polynomial_in(variable)
+                            && right.is_constant()
+                            && right
+                                .evaluate_with(&())
+                                .is_ok_and(|exponent| exponent >= 0.0 && exponent.fract() == 0.0))
+                }
+            },
+            Expr::Call { .. } => free_of(self),
+        }
+    }
+
+    fn names(&self) -> Names {
+        let mut names = Names::default();
+        self.visit(&mut names);
+        names
+    }
+
+    fn shape(&self) -> Shape {
+        let mut shape = Shape::default();
+        self.visit(&mut shape);
+        shape
+    }
+}
+
+impl ExpressionNode {
+    /// The variables the expression reads, in order of first appearance.
+    pub fn variables(&self) -> Vec<String> {
+        Expr::from(self).variables()
+    }
+
+    /// The functions the expression calls, in order of first appearance.
+    pub fn functions(&self) -> Vec<String> {
+        Expr::from(self).functions()
+    }
+
+    ///
+    /// Number of nodes on the longest path from the root to a leaf of the equivalent [`Expr`],
+    /// where implicit multiplication and parentheses do not count as nodes of their own.
+    ///
+    pub fn depth(&self) -> usize {
+        Expr::from(self).depth()
+    }
+
+    /// Number of nodes in the equivalent [`Expr`].
+    pub fn node_count(&self) -> usize {
+        Expr::from(self).node_count()
+    }
+
+    /// Whether the expression reads no variables, so it evaluates to the same value every time.
+    pub fn is_constant(&self) -> bool {
+        Expr::from(self).is_constant()
+    }
+
+    /// Whether the expression is a polynomial in `variable`, see [`Expr::is_polynomial_in`].
+    pub fn is_polynomial_in(&self, variable: &str) -> bool {
+        Expr::from(self).is_polynomial_in(variable)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{parse, parse_expr};
+
+    #[test]
+    fn variables_and_functions() {
+        let node = parse("y*x + atan2(x, z) - csc(y°) + e").unwrap();
+        assert_eq!(node.variables(), ["y", "x", "z"]);
+        assert_eq!(node.functions(), ["atan2", "cosec"]);
+        assert!(parse("pi/2").unwrap().variables().is_empty());
+        assert!(parse("x").unwrap().functions().is_empty());
+    }
+
+    #[test]
+    fn depth_and_node_count() {
+        let cases = [
+            ("x", 1, 1),
+            ("-x", 2, 2),
+            ("x + 1", 2, 3),
+            ("2(x+1)", 3, 5),
+            ("sin(x^2)", 3, 4),
+            ("log(2, x) * y", 3, 5),
+        ];
+        for (source, depth, node_count) in cases {
+            let node = parse(source).unwrap();
+            assert_eq!(node.depth(), depth, "{}", source);
+            assert_eq!(node.node_count(), node_count, "{}", source);
+        }
+    }
+
+    #[test]
+    fn constant_expressions() {
+        assert!(parse("2*pi + sqrt(2)").unwrap().is_constant());
+        assert!(parse("30°").unwrap().is_constant());
+        assert!(!parse("2x").unwrap().is_constant());
+        assert!(!parse("ln(1 + t)").unwrap().is_constant());
+    }
+
+    #[test]
+    fn polynomials() {
+        let polynomial = |source: &str| parse_expr(source).unwrap().is_polynomial_in("x");
+        assert!(polynomial("7"));
+        assert!(polynomial("y"));
+        assert!(polynomial("x^3 - 2x^2 + x/4 - 1"));
+        assert!(polynomial("(x+1)^2 * (x-1)"));
+        assert!(polynomial("a*x^(1+1) + exp(a)"));
+        assert!(polynomial("-x^0"));
+        assert!(polynomial("2^y"));
+        assert!(!polynomial("x^-1"));
+        assert!(!polynomial("x^0.5"));
+        assert!(!polynomial("2^x"));
+        assert!(!polynomial("x/(x+1)"));
+        assert!(!polynomial("x % 2"));
+        assert!(!polynomial("sin(x)"));
+        assert!(parse("x*y^2").unwrap().is_polynomial_in("y"));
+    }
+}
@@ -0,0 +1,382 @@
+//!
+//! Evaluation of an expression over whole columns of inputs at once. The compiled program
+//! runs instruction by instruction over chunks of the columns, so each operator is a tight
+//! loop over contiguous values rather than a call per element.
+//!
+
+use crate::compile::{CompiledExpr, Instruction};
+use crate::expr::Expr;
+use crate::node::{EvalError, EvalErrorKind, ExpressionNode};
+use crate::operator::Operator;
+
+/// Number of elements evaluated together, keeping the stack of columns in cache.
+const CHUNK: usize = 1024;
+
+///
+/// Elements of a batch evaluation that failed, such as `ln` of a negative input, with the
+/// first error of each. The output of a failed element is NaN.
+///
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BatchErrors {
+    errors: Vec<(usize, EvalError)>,
+}
+
+impl BatchErrors {
+    /// Whether every element evaluated.
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Number of elements that failed.
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    /// Error of the element at `index`, if it failed.
+    pub fn get(&self, index: usize) -> Option<&EvalError> {
+        self.errors
+            .binary_search_by_key(&index, |(failed, _)| *failed)
+            .ok()
+            .map(|position| &self.errors[position].1)
+    }
+
+    /// Failed elements with their errors, by increasing index.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &EvalError)> {
+        self.errors.iter().map(|(index, error)| (*index, error))
+    }
+
+    /// For each of `len` elements, whether it evaluated.
+    pub fn mask(&self, len: usize) -> Vec<bool> {
+        let mut mask = vec![true; len];
+        for (index, _) in &self.errors {
+            mask[*index] = false;
+        }
+        mask
+    }
+}
+
+impl CompiledExpr {
+    ///
+    /// Evaluate over `columns` of values for the variables, in the order of
+    /// [`CompiledExpr::variables`], writing element `i` of the result to `out[i]`.
+    ///
+    /// Fewer columns than variables fail with [`EvalErrorKind::NoSubstitute`] naming the
+    /// first variable without one. Elements failing on their own are reported in the
+    /// returned [`BatchErrors`].
+    ///
+    /// # Panics
+    ///
+    /// If a column is not as long as `out`.
+    ///
+    pub fn eval_columns(
+        &self,
+        columns: &[&[f64]],
+        out: &mut [f64],
+    ) -> Result<BatchErrors, EvalError> {
+        if let Some(variable) = self.variables.get(columns.len()) {
+            return Err(EvalError {
+                kind: EvalErrorKind::NoSubstitute(variable.clone()),
+            });
+        }
+        assert!(
+            columns.iter().all(|column| column.len() == out.len()),
+            "every column must be as long as the output"
+        );
+
+        let mut stack = vec![[0.0; CHUNK]; self.stack_size];
+        let mut failed = vec![false; out.len()];
+        let mut errors = Vec::new();
+        for start in (0..out.len()).step_by(CHUNK) {
+            let len = CHUNK.min(out.len() - start);
+            self.run_chunk(columns, start, len, &mut stack, &mut failed, &mut errors);
+            out[start..start + len].copy_from_slice(&stack[0][..len]);
+        }
+        for (index, _) in &errors {
+            out[*index] = f64::NAN;
+        }
+        errors.sort_by_key(|(index, _)| *index);
+        Ok(BatchErrors { errors })
+    }
+
+    fn run_chunk(
+        &self,
+        columns: &[&[f64]],
+        start: usize,
+        len: usize,
+        stack: &mut [[f64; CHUNK]],
+        failed: &mut [bool],
+        errors: &mut Vec<(usize, EvalError)>,
+    ) {
+        let mut top = 0;
+        for instruction in &self.program {
+            match instruction {
+                Instruction::Push(value) => {
+                    stack[top][..len].fill(*value);
+                    top += 1;
+                }
+                Instruction::Load(index) => {
+                    stack[top][..len].copy_from_slice(&columns[*index][start..start + len]);
+                    top += 1;
+                }
+                Instruction::Negate => stack[top - 1][..len].iter_mut().for_each(|v| *v = -*v),
+                Instruction::Degrees(mode) => stack[top - 1][..len]
+                    .iter_mut()
+                    .for_each(|v| *v = mode.from_degrees(*v)),
+                Instruction::Binary(operator) => {
+                    top -= 1;
+                    let (lower, upper) = stack.split_at_mut(top);
+                    apply_columns(*operator, &mut lower[top - 1][..len], &upper[0][..len]);
+                }
+                Instruction::Trigonometric(trig_fn, mode) => stack[top - 1][..len]
+                    .iter_mut()
+                    .for_each(|v| *v = trig_fn.evaluate_in(*v, *mode)),
+                Instruction::Call {
+                    function,
+                    args,
+                    angle_mode,
+                } => {
+                    top -= args;
+                    let mut values = vec![0.0; *args];
+                    for i in 0..len {
+                        for (value, column) in values.iter_mut().zip(&stack[top..top + args]) {
+                            *value = column[i];
+                        }
+                        stack[top][i] = match function.call(&values) {
+                            Ok(value) => match angle_mode {
+                                Some(mode) => mode.from_radians(value),
+                                None => value,
+                            },
+                            Err(error) => {
+                                if !failed[start + i] {
+                                    failed[start + i] = true;
+                                    errors.push((start + i, error));
+                                }
+                                f64::NAN
+                            }
+                        };
+                    }
+                    top += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Apply `operator` element by element, replacing `left` with the result.
+fn apply_columns(operator: Operator, left: &mut [f64], right: &[f64]) {
+    let pairs = left.iter_mut().zip(right);
+    match operator {
+        Operator::Add => pairs.for_each(|(l, r)| *l += r),
+        Operator::Substract => pairs.for_each(|(l, r)| *l -= r),
+        Operator::Multiply => pairs.for_each(|(l, r)| *l *= r),
+        Operator::Divide => pairs.for_each(|(l, r)| *l /= r),
+        Operator::Modulus => pairs.for_each(|(l, r)| *l %= r),
+        Operator::Power => pairs.for_each(|(l, r)| *l = l.powf(*r)),
+    }
+}
+
+/// Columns of `compiled` in the order of its variables, taken by name from `columns`.
+fn order_columns<'a>(
+    compiled: &CompiledExpr,
+    columns: &[(&str, &'a [f64])],
+) -> Result<Vec<&'a [f64]>, EvalError> {
+    compiled
+        .variables()
+        .iter()
+        .map(|variable| {
+            columns
+                .iter()
+                .find(|(name, _)| name == variable)
+                .map(|(_, column)| *column)
+                .ok_or_else(|| EvalError {
+                    kind: EvalErrorKind::NoSubstitute(variable.clone()),
+                })
+        })
+        .collect()
+}
+
+impl Expr {
+    ///
+    /// Evaluate with `variable` bound to each of `xs` in turn, writing the results to `out`,
+    /// with the built-in functions and angles in radians.
+    ///
+    /// Any other variable fails the whole batch with [`EvalErrorKind::NoSubstitute`].
+    /// Elements failing on their own are reported in the returned [`BatchErrors`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use expression_engine::parse;
+    ///
+    /// let xs = [-1.0, 1.0, 4.0];
+    /// let mut out = [0.0; 3];
+    /// let errors = parse("sqrt(x) + 1").unwrap().evaluate_many("x", &xs, &mut out).unwrap();
+    ///
+    /// assert_eq!(out[1..], [2.0, 3.0]);
+    /// assert_eq!(errors.mask(xs.len()), [false, true, true]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If `out` is not as long as `xs`.
+    ///
+    pub fn evaluate_many(
+        &self,
+        variable: &str,
+        xs: &[f64],
+        out: &mut [f64],
+    ) -> Result<BatchErrors, EvalError> {
+        self.evaluate_columns(&[(variable, xs)], out)
+    }
+
+    ///
+    /// Evaluate with each variable bound to the column of that name, writing element `i` of
+    /// the result to `out[i]`, with the built-in functions and angles in radians.
+    ///
+    /// # Panics
+    ///
+    /// If a column is not as long as `out`.
+    ///
+    pub fn evaluate_columns(
+        &self,
+        columns: &[(&str, &[f64])],
+        out: &mut [f64],
+    ) -> Result<BatchErrors, EvalError> {
+        assert!(
+            columns.iter().all(|(_, column)| column.len() == out.len()),
+            "every column must be as long as the output"
+        );
+        let compiled = self.compile()?;
+        compiled.eval_columns(&order_columns(&compiled, columns)?, out)
+    }
+}
+
+impl ExpressionNode {
+    /// Evaluate with `variable` bound to each of `xs` in turn, see [`Expr::evaluate_many`].
+    pub fn evaluate_many(
+        &self,
+        variable: &str,
+        xs: &[f64],
+        out: &mut [f64],
+    ) -> Result<BatchErrors, EvalError> {
+        Expr::from(self).evaluate_many(variable, xs, out)
+    }
+
+    /// Evaluate with variables bound to columns of values, see [`Expr::evaluate_columns`].
+    pub fn evaluate_columns(
+        &self,
+        columns: &[(&str, &[f64])],
+        out: &mut [f64],
+    ) -> Result<BatchErrors, EvalError> {
+        Expr::from(self).evaluate_columns(columns, out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn evaluate_many_matches_evaluate() {
+        let xs: Vec<f64> = (0..2500).map(|i| i as f64 * 0.01 - 12.0).collect();
+        let mut out = vec![0.0; xs.len()];
+        for source in [
+            "3x^2 - 2x + 1",
+            "-x % 2.5 / (x + 1)",
+            "sin(x°) * atan2(x, 2) + abs(x)",
+        ] {
+            let exp = parse(source).unwrap();
+            let errors = exp.evaluate_many("x", &xs, &mut out).unwrap();
+            assert!(errors.is_empty());
+            for (x, y) in xs.iter().zip(&out) {
+                let expected = exp.evaluate("x", *x).unwrap();
+                assert!(
+                    expected == *y || (expected.is_nan() && y.is_nan()),
+                    "{} at {}",
+                    source,
+                    x
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn evaluate_many_element_errors() {
+        let xs = [1.0, -1.0, 0.0, 4.0, -2.0];
+        let mut out = [0.0; 5];
+        let errors = parse("ln(x) + sqrt(x)")
+            .unwrap()
+            .evaluate_many("x", &xs, &mut out)
+            .unwrap();
+
+        assert_eq!(errors.len(), 3);
+        assert_eq!(errors.mask(xs.len()), [true, false, false, true, false]);
+        assert_eq!(
+            errors.get(1).unwrap().kind,
+            EvalErrorKind::DomainError {
+                function: String::from("ln"),
+                value: -1.0
+            }
+        );
+        assert!(errors.get(0).is_none());
+        assert_eq!(
+            errors.iter().map(|(index, _)| index).collect::<Vec<_>>(),
+            [1, 2, 4]
+        );
+        assert_eq!(out[0], 1.0);
+        assert!(out[1].is_nan() && out[2].is_nan() && out[4].is_nan());
+        assert_eq!(out[3], 4.0f64.ln() + 2.0);
+    }
+
+    #[test]
+    fn evaluate_columns_by_name() {
+        let exp = parse("x*y + z").unwrap();
+        let mut out = [0.0; 3];
+        let errors = exp
+            .evaluate_columns(
+                &[
+                    ("z", &[0.5, 0.5, 0.5]),
+                    ("x", &[1.0, 2.0, 3.0]),
+                    ("y", &[4.0, 5.0, 6.0]),
+                ],
+                &mut out,
+            )
+            .unwrap();
+        assert!(errors.is_empty());
+        assert_eq!(out, [4.5, 10.5, 18.5]);
+
+        assert_eq!(
+            exp.evaluate_many("x", &[1.0, 2.0, 3.0], &mut out)
+                .unwrap_err()
+                .kind,
+            EvalErrorKind::NoSubstitute(String::from("y"))
+        );
+    }
+
+    #[test]
+    fn evaluate_many_without_variables() {
+        let mut out = [0.0; 4];
+        parse("2pi")
+            .unwrap()
+            .evaluate_many("x", &[0.0; 4], &mut out)
+            .unwrap();
+        assert_eq!(out, [std::f64::consts::TAU; 4]);
+
+        let mut empty: [f64; 0] = [];
+        assert!(parse("x + 1")
+            .unwrap()
+            .evaluate_many("x", &[], &mut empty)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "every column must be as long as the output")]
+    fn evaluate_many_length_mismatch() {
+        let mut out = [0.0; 2];
+        let _ = parse("x")
+            .unwrap()
+            .evaluate_many("x", &[1.0, 2.0, 3.0], &mut out);
+    }
+}
@@ -0,0 +1,358 @@
+//!
+//! Lowering of expression trees to flat programs for a stack machine, evaluated in a single
+//! pass without recursion, function lookups or variable lookups by name.
+//!
+
+use crate::context::Context;
+use crate::expr::{Expr, UnaryOperator};
+use crate::function::Function;
+use crate::node::{EvalError, EvalErrorKind, ExpressionNode};
+use crate::operator::Operator;
+use crate::trigonometric::{AngleMode, TrigonometricFunction};
+
+/// Stack size evaluated without allocating, deeper programs allocate their stack per evaluation.
+const INLINE_STACK: usize = 32;
+
+/// One step of a [`CompiledExpr`], popping its operands and pushing its result.
+#[derive(Debug, Clone)]
+pub(crate) enum Instruction {
+    Push(f64),
+    /// Push the value of the variable at this index.
+    Load(usize),
+    Negate,
+    Degrees(AngleMode),
+    Binary(Operator),
+    Trigonometric(TrigonometricFunction, AngleMode),
+    Call {
+        function: Function,
+        args: usize,
+        /// Mode to convert the result to, for functions returning an angle.
+        angle_mode: Option<AngleMode>,
+    },
+}
+
+impl Instruction {
+    /// Number of values the instruction pops, it always pushes one.
+    fn pops(&self) -> usize {
+        match self {
+            Instruction::Push(_) | Instruction::Load(_) => 0,
+            Instruction::Binary(_) => 2,
+            Instruction::Call { args, .. } => *args,
+            _ => 1,
+        }
+    }
+}
+
+///
+/// An expression compiled to a program for a stack machine, for evaluating the same
+/// expression many times.
+///
+/// Functions, their argument counts and the angle mode are resolved when compiling, and
+/// variables are numbered in order of first appearance, see [`CompiledExpr::variables`].
+/// Evaluation follows [`crate::EvalMode::Ieee`] whatever the mode of the compiling context.
+///
+/// # Examples
+///
+/// ```
+/// use expression_engine::parse;
+///
+/// let compiled = parse("a*x^2 + b").unwrap().compile().unwrap();
+/// assert_eq!(compiled.variables(), ["a", "x", "b"]);
+/// assert_eq!(compiled.eval(&[2.0, 3.0, 1.0]).unwrap(), 19.0);
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct CompiledExpr {
+    pub(crate) program: Vec<Instruction>,
+    pub(crate) variables: Vec<String>,
+    pub(crate) stack_size: usize,
+}
+
+impl CompiledExpr {
+    /// The variables in the order [`CompiledExpr::eval`] takes their values.
+    pub fn variables(&self) -> &[String] {
+        &self.variables
+    }
+
+    ///
+    /// Evaluate with `values` for the variables, in the order of [`CompiledExpr::variables`].
+    /// Fewer values than variables fail with [`EvalErrorKind::NoSubstitute`] naming the first
+    /// variable without a value, extra values are ignored.
+    ///
+    pub fn eval(&self, values: &[f64]) -> Result<f64, EvalError> {
+        if let Some(variable) = self.variables.get(values.len()) {
+            return Err(EvalError {
+                kind: EvalErrorKind::NoSubstitute(variable.clone()),
+            });
+        }
+        if self.stack_size <= INLINE_STACK {
+            self.run(values, &mut [0.0; INLINE_STACK])
+        } else {
+            self.run(values, &mut vec![0.0; self.stack_size])
+        }
+    }
+
+    fn run(&self, values: &[f64], stack: &mut [f64]) -> Result<f64, EvalError> {
+        let mut top = 0;
+        for instruction in &self.program {
+            match instruction {
+                Instruction::Push(value) => {
+                    stack[top] = *value;
+                    top += 1;
+                }
+                Instruction::Load(index) => {
+                    stack[top] = values[*index];
+                    top += 1;
+                }
+                Instruction::Negate => stack[top - 1] = -stack[top - 1],
+                Instruction::Degrees(mode) => stack[top - 1] = mode.from_degrees(stack[top - 1]),
+                Instruction::Binary(operator) => {
+                    top -= 1;
+                    stack[top - 1] = operator.apply(stack[top - 1], stack[top]);
+                }
+                Instruction::Trigonometric(trig_fn, mode) => {
+                    stack[top - 1] = trig_fn.evaluate_in(stack[top - 1], *mode)
+                }
+                Instruction::Call {
+                    function,
+                    args,
+                    angle_mode,
+                } => {
+                    top -= args;
+                    let value = function.call(&stack[top..top + args])?;
+                    stack[top] = match angle_mode {
+                        Some(mode) => mode.from_radians(value),
+                        None => value,
+                    };
+                    top += 1;
+                }
+            }
+        }
+        Ok(stack[0])
+    }
+}
+
+/// Builds the program of a [`CompiledExpr`], tracking how deep the stack gets.
+struct Compiler<'c, C: Context + ?Sized> {
+    ctx: &'c C,
+    program: Vec<Instruction>,
+    variables: Vec<String>,
+    depth: usize,
+    stack_size: usize,
+}
+
+impl<C: Context + ?Sized> Compiler<'_, C> {
+    fn emit(&mut self, instruction: Instruction) {
+        self.depth = self.depth - instruction.pops() + 1;
+        self.stack_size = self.stack_size.max(self.depth);
+        self.program.push(instruction);
+    }
+
+    fn compile(&mut self, expr: &Expr) -> Result<(), EvalError> {
+        match expr {
+            Expr::Number { value, .. } | Expr::Constant { value, .. } => {
+                self.emit(Instruction::Push(*value))
+            }
+            Expr::Variable(name) => {
+                let index = match self.variables.iter().position(|variable| variable == name) {
+                    Some(index) => index,
+                    None => {
+                        self.variables.push(name.clone());
+                        self.variables.len() - 1
+                    }
+                };
+                self.emit(Instruction::Load(index))
+            }
+            Expr::Unary { op, operand } => {
+                self.compile(operand)?;
+                self.emit(match op {
+                    UnaryOperator::Negate => Instruction::Negate,
+                    UnaryOperator::Degrees => Instruction::Degrees(self.ctx.angle_mode()),
+                })
+            }
+            Expr::Binary { op, left, right } => {
+                self.compile(left)?;
+                self.compile(right)?;
+                self.emit(Instruction::Binary(*op))
+            }
+            Expr::Call { name, args } => match (TrigonometricFunction::from(name), &args[..]) {
+                (Some(trig_fn), [arg]) => {
+                    self.compile(arg)?;
+                    self.emit(Instruction::Trigonometric(trig_fn, self.ctx.angle_mode()))
+                }
+                _ => {
+                    let function = self.ctx.function(name).ok_or_else(|| EvalError {
+                        kind: EvalErrorKind::UnknownFunction(name.clone()),
+                    })?;
+                    if !function.arity().accepts(args.len()) {
+                        return Err(EvalError {
+                            kind: EvalErrorKind::InvalidExpression,
+                        });
+                    }
+                    let instruction = Instruction::Call {
+                        function: function.clone(),
+                        args: args.len(),
+                        angle_mode: function.returns_angle().then(|| self.ctx.angle_mode()),
+                    };
+                    for arg in args {
+                        self.compile(arg)?;
+                    }
+                    self.emit(instruction)
+                }
+            },
+        }
+        Ok(())
+    }
+}
+
+impl Expr {
+    /// Compile the expression with the built-in functions and angles in radians.
+    pub fn compile(&self) -> Result<CompiledExpr, EvalError> {
+        self.compile_with(&())
+    }
+
+    ///
+    /// Compile the expression with the functions and angle mode of `ctx`. Unknown functions
+    /// fail with [`EvalErrorKind::UnknownFunction`] and calls with a number of arguments the
+    /// function does not accept with [`EvalErrorKind::InvalidExpression`].
+    ///
+    pub fn compile_with(&self, ctx: &(impl Context + ?Sized)) -> Result<CompiledExpr, EvalError> {
+        let mut compiler = Compiler {
+            ctx,
+            program: Vec::new(),
+            variables: Vec::new(),
+            depth: 0,
+            stack_size: 0,
+        };
+        compiler.compile(self)?;
+        Ok(CompiledExpr {
+            program: compiler.program,
+            variables: compiler.variables,
+            stack_size: compiler.stack_size,
+        })
+    }
+}
+
+impl ExpressionNode {
+    /// Compile the expression with the built-in functions and angles in radians.
+    pub fn compile(&self) -> Result<CompiledExpr, EvalError> {
+        Expr::from(self).compile()
+    }
+
+    /// Compile the expression with the functions and angle mode of `ctx`, see [`Expr::compile_with`].
+    pub fn compile_with(&self, ctx: &(impl Context + ?Sized)) -> Result<CompiledExpr, EvalError> {
+        Expr::from(self).compile_with(ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::Environment;
+    use crate::function::Arity;
+    use crate::parser::{parse, parse_with};
+    use std::collections::HashMap;
+
+    #[test]
+    fn compile_matches_tree() {
+        let sources = [
+            "x",
+            "3x^2 - 2x + 1",
+            "-x^2 % 0.7",
+            "2^-x / (1 + x)",
+            "sin(x) * cos x + tan(30°)",
+            "atan2(x, 2) + asinh x",
+            "log(2, abs(x) + 1) - sqrt(x^2) + 0x10",
+            "[x + 1]{x - 1}(x)",
+        ];
+        for source in sources {
+            let exp = parse(source).unwrap();
+            let compiled = exp.compile().unwrap();
+            for x in [-2.5, -1.0, 0.0, 0.5, 3.0] {
+                let expected = exp.evaluate("x", x);
+                let actual = compiled.eval(&[x]);
+                match (expected, actual) {
+                    (Ok(expected), Ok(actual)) => {
+                        assert!(
+                            expected == actual || (expected.is_nan() && actual.is_nan()),
+                            "{} at {}: {} != {}",
+                            source,
+                            x,
+                            expected,
+                            actual
+                        )
+                    }
+                    (expected, actual) => assert_eq!(expected, actual, "{} at {}", source, x),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn compile_variables() {
+        let exp = parse("y*x + z - x").unwrap();
+        let compiled = exp.compile().unwrap();
+        assert_eq!(compiled.variables(), ["y", "x", "z"]);
+        assert_eq!(compiled.eval(&[2.0, 3.0, 4.0]).unwrap(), 7.0);
+        assert_eq!(
+            compiled.eval(&[2.0, 3.0]).unwrap_err().kind,
+            EvalErrorKind::NoSubstitute(String::from("z"))
+        );
+        assert!(parse("pi")
+            .unwrap()
+            .compile()
+            .unwrap()
+            .variables()
+            .is_empty());
+    }
+
+    #[test]
+    fn compile_with_context() {
+        let mut env = Environment::new();
+        env.register_function("lerp", Arity::Exact(3), |args| {
+            Ok(args[0] + (args[1] - args[0]) * args[2])
+        });
+        env.set_angle_mode(AngleMode::Degrees);
+        let exp = parse_with("lerp(0, 10, t) + sin(90) + atan2(1, 1)", &env).unwrap();
+        let compiled = exp.compile_with(&env).unwrap();
+        env.set("t", 0.5);
+        assert_eq!(
+            compiled.eval(&[0.5]).unwrap(),
+            exp.evaluate_with(&env).unwrap()
+        );
+        assert_eq!(compiled.eval(&[0.5]).unwrap(), 51.0);
+
+        assert_eq!(
+            exp.compile().unwrap_err().kind,
+            EvalErrorKind::UnknownFunction(String::from("lerp"))
+        );
+        assert_eq!(
+            parse("ln(x)")
+                .unwrap()
+                .compile()
+                .unwrap()
+                .eval(&[-1.0])
+                .unwrap_err()
+                .kind,
+            EvalErrorKind::DomainError {
+                function: String::from("ln"),
+                value: -1.0
+            }
+        );
+    }
+
+    #[test]
+    fn compile_deep_stack() {
+        let source = format!("{}x{}", "1+(".repeat(40), ")".repeat(40));
+        let compiled = parse(&source).unwrap().compile().unwrap();
+        assert!(compiled.stack_size > INLINE_STACK);
+        assert_eq!(compiled.eval(&[2.0]).unwrap(), 42.0);
+        assert_eq!(
+            compiled.eval(&[2.0]).unwrap(),
+            parse(&source)
+                .unwrap()
+                .evaluate_with(&HashMap::from([("x", 2.0)]))
+                .unwrap()
+        );
+    }
+}
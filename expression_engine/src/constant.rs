@@ -0,0 +1,46 @@
+use std::f64::consts::{E, PI, TAU};
+
+/// Golden ratio (1+√5)/2.
+const PHI: f64 = 1.618_033_988_749_895;
+
+///
+/// Value of a built-in named constant.
+///
+/// | Name          | Value              |
+/// |---------------|--------------------|
+/// | `pi`, `π`     | 3.14159…           |
+/// | `tau`, `τ`    | 6.28318…           |
+/// | `e`           | 2.71828…           |
+/// | `phi`         | 1.61803…           |
+/// | `inf`         | positive infinity  |
+/// | `nan`         | not a number       |
+///
+pub fn builtin_constant(name: &str) -> Option<f64> {
+    match name {
+        "pi" | "π" => Some(PI),
+        "tau" | "τ" => Some(TAU),
+        "e" => Some(E),
+        "phi" => Some(PHI),
+        "inf" => Some(f64::INFINITY),
+        "nan" => Some(f64::NAN),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_constants() {
+        assert_eq!(builtin_constant("pi"), Some(PI));
+        assert_eq!(builtin_constant("π"), Some(PI));
+        assert_eq!(builtin_constant("tau"), Some(2.0 * PI));
+        assert_eq!(builtin_constant("τ"), Some(2.0 * PI));
+        assert_eq!(builtin_constant("e"), Some(1.0f64.exp()));
+        assert_eq!(builtin_constant("phi"), Some((1.0 + 5.0f64.sqrt()) / 2.0));
+        assert_eq!(builtin_constant("inf"), Some(f64::INFINITY));
+        assert!(builtin_constant("nan").is_some_and(f64::is_nan));
+        assert_eq!(builtin_constant("x"), None);
+    }
+}
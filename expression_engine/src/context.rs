@@ -0,0 +1,267 @@
+use crate::constant::builtin_constant;
+use crate::function::{Arity, Function, FunctionRegistry};
+use crate::node::{EvalError, EvalMode};
+use crate::trigonometric::AngleMode;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+
+///
+/// Source of values for the variables of an expression during evaluation, and of the
+/// named constants and functions recognised while parsing.
+///
+/// Implemented for `HashMap`s keyed by variable name, for a single `(name, value)` pair,
+/// for `()` binding no variables and for [`Environment`]. Implement it on your own store to evaluate against it directly.
+///
+/// # Examples
+///
+/// ```
+/// use expression_engine::parse;
+/// use std::collections::HashMap;
+///
+/// let exp = parse("x*y+1").unwrap();
+/// let variables = HashMap::from([("x", 2.0), ("y", 3.0)]);
+///
+/// assert_eq!(exp.evaluate_with(&variables).unwrap(), 7.0);
+/// assert!(exp.evaluate_with(&("x", 2.0)).is_err());
+/// ```
+///
+pub trait Context {
+    fn variable(&self, name: &str) -> Option<f64>;
+
+    /// Value of a named constant, the built-in `pi`, `e`, `tau`, `phi`, `inf` and `nan` by default.
+    fn constant(&self, name: &str) -> Option<f64> {
+        builtin_constant(name)
+    }
+
+    /// Function callable by name, the functions of [`FunctionRegistry::builtin`] by default.
+    fn function(&self, name: &str) -> Option<&Function> {
+        FunctionRegistry::builtin().get(name)
+    }
+
+    /// Names of the functions callable through [`Context::function`], in no particular order.
+    fn function_names(&self) -> Vec<&str> {
+        FunctionRegistry::builtin().names().collect()
+    }
+
+    /// Unit of the angles taken and returned by trigonometric functions, radians by default.
+    fn angle_mode(&self) -> AngleMode {
+        AngleMode::Radians
+    }
+
+    /// How evaluation treats division by zero and results that are not real numbers, IEEE 754 by default.
+    fn eval_mode(&self) -> EvalMode {
+        EvalMode::Ieee
+    }
+}
+
+impl Context for () {
+    fn variable(&self, _name: &str) -> Option<f64> {
+        None
+    }
+}
+
+impl<S: BuildHasher> Context for HashMap<String, f64, S> {
+    fn variable(&self, name: &str) -> Option<f64> {
+        self.get(name).copied()
+    }
+}
+
+impl<S: BuildHasher> Context for HashMap<&str, f64, S> {
+    fn variable(&self, name: &str) -> Option<f64> {
+        self.get(name).copied()
+    }
+}
+
+impl Context for (&str, f64) {
+    fn variable(&self, name: &str) -> Option<f64> {
+        if self.0 == name {
+            Some(self.1)
+        } else {
+            None
+        }
+    }
+}
+
+impl<C: Context + ?Sized> Context for &C {
+    fn variable(&self, name: &str) -> Option<f64> {
+        (**self).variable(name)
+    }
+
+    fn constant(&self, name: &str) -> Option<f64> {
+        (**self).constant(name)
+    }
+
+    fn function(&self, name: &str) -> Option<&Function> {
+        (**self).function(name)
+    }
+
+    fn function_names(&self) -> Vec<&str> {
+        (**self).function_names()
+    }
+
+    fn angle_mode(&self) -> AngleMode {
+        (**self).angle_mode()
+    }
+
+    fn eval_mode(&self) -> EvalMode {
+        (**self).eval_mode()
+    }
+}
+
+///
+/// Variable bindings, additional named constants and functions owned by name,
+/// the angle mode of trigonometric functions and the evaluation mode.
+///
+/// # Examples
+///
+/// ```
+/// use expression_engine::{parse, AngleMode, Environment};
+///
+/// let mut env = Environment::new();
+/// env.set_angle_mode(AngleMode::Degrees);
+///
+/// assert!((parse("sin(30)").unwrap().evaluate_with(&env).unwrap() - 0.5).abs() < 1e-12);
+/// assert!((parse("atan(1)").unwrap().evaluate_with(&env).unwrap() - 45.0).abs() < 1e-12);
+/// ```
+///
+#[derive(Debug, Clone, Default)]
+pub struct Environment {
+    variables: HashMap<String, f64>,
+    constants: HashMap<String, f64>,
+    functions: FunctionRegistry,
+    angle_mode: AngleMode,
+    eval_mode: EvalMode,
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bind `name` to `value`, replacing any previous binding.
+    pub fn set(&mut self, name: &str, value: f64) {
+        self.variables.insert(name.to_string(), value);
+    }
+
+    pub fn get(&self, name: &str) -> Option<f64> {
+        self.variables.get(name).copied()
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<f64> {
+        self.variables.remove(name)
+    }
+
+    ///
+    /// Register a named constant, recognised by [`crate::parse_with`] alongside the
+    /// built-in ones. A constant shadows the built-in constant of the same name.
+    ///
+    pub fn set_constant(&mut self, name: &str, value: f64) {
+        self.constants.insert(name.to_string(), value);
+    }
+
+    /// Register a function, recognised by [`crate::parse_with`] and callable during evaluation.
+    pub fn register_function(
+        &mut self,
+        name: &str,
+        arity: Arity,
+        callback: impl Fn(&[f64]) -> Result<f64, EvalError> + Send + Sync + 'static,
+    ) {
+        self.functions.register(name, arity, callback);
+    }
+
+    pub fn functions(&self) -> &FunctionRegistry {
+        &self.functions
+    }
+
+    pub fn functions_mut(&mut self) -> &mut FunctionRegistry {
+        &mut self.functions
+    }
+
+    pub fn set_angle_mode(&mut self, angle_mode: AngleMode) {
+        self.angle_mode = angle_mode;
+    }
+
+    pub fn set_eval_mode(&mut self, eval_mode: EvalMode) {
+        self.eval_mode = eval_mode;
+    }
+}
+
+impl Context for Environment {
+    fn variable(&self, name: &str) -> Option<f64> {
+        self.get(name)
+    }
+
+    fn constant(&self, name: &str) -> Option<f64> {
+        self.constants
+            .get(name)
+            .copied()
+            .or_else(|| builtin_constant(name))
+    }
+
+    fn function(&self, name: &str) -> Option<&Function> {
+        self.functions.get(name)
+    }
+
+    fn function_names(&self) -> Vec<&str> {
+        self.functions.names().collect()
+    }
+
+    fn angle_mode(&self) -> AngleMode {
+        self.angle_mode
+    }
+
+    fn eval_mode(&self) -> EvalMode {
+        self.eval_mode
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn context_hash_map() {
+        let variables = HashMap::from([(String::from("x"), 1.5)]);
+        assert_eq!(variables.variable("x"), Some(1.5));
+        assert_eq!(variables.variable("y"), None);
+
+        let variables = HashMap::from([("x", 1.5)]);
+        assert_eq!(variables.variable("x"), Some(1.5));
+        assert_eq!(variables.variable("y"), None);
+    }
+
+    #[test]
+    fn context_pair() {
+        assert_eq!(("x", 1.5).variable("x"), Some(1.5));
+        assert_eq!(("x", 1.5).variable("y"), None);
+    }
+
+    #[test]
+    fn context_environment() {
+        let mut env = Environment::new();
+        env.set("x", 1.5);
+        env.set("x", 2.5);
+        assert_eq!(env.variable("x"), Some(2.5));
+        assert_eq!(env.remove("x"), Some(2.5));
+        assert_eq!(env.variable("x"), None);
+
+        env.set_constant("g", 9.81);
+        env.set_constant("e", 1.0);
+        assert_eq!(env.constant("g"), Some(9.81));
+        assert_eq!(env.constant("e"), Some(1.0));
+        assert_eq!(env.constant("pi"), Some(std::f64::consts::PI));
+        assert_eq!(env.variable("g"), None);
+
+        env.register_function("double", Arity::Exact(1), |args| Ok(2.0 * args[0]));
+        assert_eq!(env.function("double").unwrap().call(&[1.5]).unwrap(), 3.0);
+        assert!(env.function("sin").is_some());
+        env.functions_mut().remove("sin");
+        assert!(env.function("sin").is_none());
+        assert!(().function("sin").is_some());
+        assert!(().function("double").is_none());
+
+        assert_eq!(env.angle_mode(), AngleMode::Radians);
+        env.set_angle_mode(AngleMode::Gradians);
+        assert_eq!(env.angle_mode(), AngleMode::Gradians);
+    }
+}
@@ -0,0 +1,411 @@
+//!
+//! Symbolic differentiation with the sum, product, quotient, power and chain rules, simplified
+//! so the derivative reads as it would be written by hand.
+//!
+
+use crate::elementary::ElementaryFunction;
+use crate::expr::{Expr, UnaryOperator};
+use crate::node::{EvalError, EvalErrorKind, ExpressionNode};
+use crate::operator::Operator;
+use crate::simplify::{binary, negate, number};
+use crate::trigonometric::{AngleMode, TrigonometricFunction};
+use std::f64::consts::{LN_10, LN_2};
+
+/// Differentiates with respect to one variable, with angles in one mode.
+struct Differentiator<'v> {
+    variable: &'v str,
+    angle_mode: AngleMode,
+}
+
+impl Differentiator<'_> {
+    fn derivative(&self, expr: &Expr) -> Result<Expr, EvalError> {
+        Ok(match expr {
+            Expr::Number { .. } | Expr::Constant { .. } => number(0.0),
+            Expr::Variable(name) => number(if name == self.variable { 1.0 } else { 0.0 }),
+            Expr::Unary {
+                op: UnaryOperator::Negate,
+                operand,
+            } => negate(self.derivative(operand)?),
+            // `u°` is `u` times a factor of the angle mode, so its derivative is `u'°`
+            Expr::Unary {
+                op: UnaryOperator::Degrees,
+                operand,
+            } => Expr::Unary {
+                op: UnaryOperator::Degrees,
+                operand: Box::new(self.derivative(operand)?),
+            },
+            Expr::Binary { op, left, right } => self.binary(*op, left, right)?,
+            Expr::Call { name, args } => self.call(name, args)?,
+        })
+    }
+
+    fn binary(&self, op: Operator, u: &Expr, v: &Expr) -> Result<Expr, EvalError> {
+        let (du, dv) = (self.derivative(u)?, self.derivative(v)?);
+        let (u, v) = (u.clone(), v.clone());
+        Ok(match op {
+            Operator::Add | Operator::Substract => binary(op, du, dv),
+            Operator::Multiply => add(mul(du, v.clone()), mul(u, dv)),
+            Operator::Divide => div(sub(mul(du, v.clone()), mul(u, dv)), pow(v, number(2.0))),
+            Operator::Power if !self.depends_on(&v) => {
+                mul(mul(v.clone(), pow(u, sub(v, number(1.0)))), du)
+            }
+            // d(u^v) = u^v * (v' ln(u) + v u'/u)
+            Operator::Power => mul(
+                pow(u.clone(), v.clone()),
+                add(mul(dv, ln(u.clone())), div(mul(v, du), u)),
+            ),
+            // u % v = u - v*trunc(u/v), where trunc(u/v) = (u - u%v)/v is piecewise constant
+            Operator::Modulus => sub(
+                du,
+                mul(div(sub(u.clone(), binary(op, u, v.clone())), v), dv),
+            ),
+        })
+    }
+
+    fn call(&self, name: &str, args: &[Expr]) -> Result<Expr, EvalError> {
+        if !args.iter().any(|arg| self.depends_on(arg)) {
+            return Ok(number(0.0));
+        }
+        let angle = number(self.angle_mode.from_radians(1.0));
+        Ok(match (TrigonometricFunction::from(name), args) {
+            (Some(trig_fn), [u]) => mul(self.trigonometric(trig_fn, u), self.derivative(u)?),
+            _ => match (ElementaryFunction::from(name), args) {
+                // log(b, u) = ln(u) / ln(b)
+                (Some(ElementaryFunction::Log), [b, u]) => sub(
+                    div(self.derivative(u)?, mul(u.clone(), ln(b.clone()))),
+                    div(
+                        mul(ln(u.clone()), self.derivative(b)?),
+                        mul(b.clone(), pow(ln(b.clone()), number(2.0))),
+                    ),
+                ),
+                (Some(function), [u]) => mul(elementary(function, u), self.derivative(u)?),
+                // atan2(y, x) is the angle of (x, y)
+                (None, [y, x]) if name == "atan2" => mul(
+                    div(
+                        sub(
+                            mul(x.clone(), self.derivative(y)?),
+                            mul(y.clone(), self.derivative(x)?),
+                        ),
+                        add(square(x), square(y)),
+                    ),
+                    angle,
+                ),
+                _ => {
+                    return Err(EvalError {
+                        kind: EvalErrorKind::NotDifferentiable(name.to_string()),
+                    })
+                }
+            },
+        })
+    }
+
+    /// The derivative of `trig_fn` at `u`, with angles in the angle mode.
+    fn trigonometric(&self, trig_fn: TrigonometricFunction, u: &Expr) -> Expr {
+        let at_u = |trig_fn: TrigonometricFunction| call(trig_fn.name(), u.clone());
+        // factors converting the argument of the trigonometric functions to radians and the
+        // result of the inverse ones from radians
+        let radians = number(self.angle_mode.to_radians(1.0));
+        let angle = number(self.angle_mode.from_radians(1.0));
+        // 1 / (u^2 sqrt(1 - 1/u^2)), the derivative of asec(u) = acos(1/u)
+        let asec = || {
+            div(
+                angle.clone(),
+                mul(
+                    square(u),
+                    call("sqrt", sub(number(1.0), div(number(1.0), square(u)))),
+                ),
+            )
+        };
+        match trig_fn {
+            TrigonometricFunction::Sin => mul(at_u(TrigonometricFunction::Cos), radians),
+            TrigonometricFunction::Cos => mul(negate(at_u(TrigonometricFunction::Sin)), radians),
+            TrigonometricFunction::Tan => {
+                mul(pow(at_u(TrigonometricFunction::Sec), number(2.0)), radians)
+            }
+            TrigonometricFunction::Sec => mul(
+                mul(
+                    at_u(TrigonometricFunction::Sec),
+                    at_u(TrigonometricFunction::Tan),
+                ),
+                radians,
+            ),
+            TrigonometricFunction::Cosec => mul(
+                negate(mul(
+                    at_u(TrigonometricFunction::Cosec),
+                    at_u(TrigonometricFunction::Cot),
+                )),
+                radians,
+            ),
+            TrigonometricFunction::Cot => mul(
+                negate(pow(at_u(TrigonometricFunction::Cosec), number(2.0))),
+                radians,
+            ),
+            TrigonometricFunction::Asin => div(angle, call("sqrt", sub(number(1.0), square(u)))),
+            TrigonometricFunction::Acos => {
+                negate(div(angle, call("sqrt", sub(number(1.0), square(u)))))
+            }
+            TrigonometricFunction::Atan => div(angle, add(number(1.0), square(u))),
+            TrigonometricFunction::Asec => asec(),
+            TrigonometricFunction::Acsc => negate(asec()),
+            TrigonometricFunction::Acot => negate(div(angle, add(number(1.0), square(u)))),
+            TrigonometricFunction::Sinh => at_u(TrigonometricFunction::Cosh),
+            TrigonometricFunction::Cosh => at_u(TrigonometricFunction::Sinh),
+            TrigonometricFunction::Tanh => div(
+                number(1.0),
+                pow(at_u(TrigonometricFunction::Cosh), number(2.0)),
+            ),
+            TrigonometricFunction::Asinh => {
+                div(number(1.0), call("sqrt", add(square(u), number(1.0))))
+            }
+            TrigonometricFunction::Acosh => {
+                div(number(1.0), call("sqrt", sub(square(u), number(1.0))))
+            }
+            TrigonometricFunction::Atanh => div(number(1.0), sub(number(1.0), square(u))),
+        }
+    }
+
+    fn depends_on(&self, expr: &Expr) -> bool {
+        expr.variables().iter().any(|name| name == self.variable)
+    }
+}
+
+/// The derivative of the one argument `function` at `u`.
+fn elementary(function: ElementaryFunction, u: &Expr) -> Expr {
+    let u = u.clone();
+    match function {
+        ElementaryFunction::Exp => call("exp", u),
+        ElementaryFunction::Ln => div(number(1.0), u),
+        ElementaryFunction::Log | ElementaryFunction::Log10 => {
+            div(number(1.0), mul(u, number(LN_10)))
+        }
+        ElementaryFunction::Log2 => div(number(1.0), mul(u, number(LN_2))),
+        ElementaryFunction::Sqrt => div(number(1.0), mul(number(2.0), call("sqrt", u))),
+        ElementaryFunction::Cbrt => div(
+            number(1.0),
+            mul(number(3.0), pow(call("cbrt", u), number(2.0))),
+        ),
+        ElementaryFunction::Abs => div(u.clone(), call("abs", u)),
+    }
+}
+
+fn add(left: Expr, right: Expr) -> Expr {
+    binary(Operator::Add, left, right)
+}
+
+fn sub(left: Expr, right: Expr) -> Expr {
+    binary(Operator::Substract, left, right)
+}
+
+fn mul(left: Expr, right: Expr) -> Expr {
+    binary(Operator::Multiply, left, right)
+}
+
+fn div(left: Expr, right: Expr) -> Expr {
+    binary(Operator::Divide, left, right)
+}
+
+fn pow(base: Expr, exponent: Expr) -> Expr {
+    binary(Operator::Power, base, exponent)
+}
+
+fn square(u: &Expr) -> Expr {
+    pow(u.clone(), number(2.0))
+}
+
+fn call(name: &str, arg: Expr) -> Expr {
+    Expr::Call {
+        name: name.to_string(),
+        args: vec![arg],
+    }
+}
+
+/// The natural logarithm of `u`, taken right away for a constant such as `e`.
+fn ln(u: Expr) -> Expr {
+    match u {
+        Expr::Constant { value, .. } => number(value.ln()),
+        u => call("ln", u),
+    }
+}
+
+impl Expr {
+    ///
+    /// The derivative with respect to `variable`, with angles in radians, simplified with
+    /// [`Expr::simplify`]. Every operator, trigonometric and elementary function and `atan2`
+    /// is differentiated; a call to any other function whose arguments read `variable` fails
+    /// with [`EvalErrorKind::NotDifferentiable`].
+    ///
+    /// The derivative of `%` treats the quotient it truncates as a constant, so it is correct
+    /// everywhere except where that quotient jumps, and that of `abs` is undefined at `0`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use expression_engine::parse_expr;
+    ///
+    /// let derivative = |source: &str| {
+    ///     parse_expr(source).unwrap().derivative("x").unwrap().to_string()
+    /// };
+    ///
+    /// assert_eq!(derivative("x^2"), "2*x");
+    /// assert_eq!(derivative("3x^2 - 4x + y"), "6*x-4");
+    /// assert_eq!(derivative("x*ln(x)"), "ln(x)+1");
+    /// assert_eq!(derivative("sin(2x)"), "2*cos(2*x)");
+    /// ```
+    ///
+    pub fn derivative(&self, variable: &str) -> Result<Expr, EvalError> {
+        self.derivative_in(variable, AngleMode::Radians)
+    }
+
+    ///
+    /// The derivative with respect to `variable` when evaluated with angles in `angle_mode`,
+    /// where the trigonometric functions pick up a factor converting their argument to
+    /// radians, and the inverse ones a factor converting their result from radians.
+    ///
+    pub fn derivative_in(&self, variable: &str, angle_mode: AngleMode) -> Result<Expr, EvalError> {
+        let differentiator = Differentiator {
+            variable,
+            angle_mode,
+        };
+        Ok(differentiator.derivative(self)?.simplify())
+    }
+}
+
+impl ExpressionNode {
+    /// The derivative with respect to `variable`, with angles in radians, see [`Expr::derivative`].
+    pub fn derivative(&self, variable: &str) -> Result<ExpressionNode, EvalError> {
+        Ok(Expr::from(self).derivative(variable)?.into())
+    }
+
+    /// The derivative with respect to `variable` with angles in `angle_mode`, see [`Expr::derivative_in`].
+    pub fn derivative_in(
+        &self,
+        variable: &str,
+        angle_mode: AngleMode,
+    ) -> Result<ExpressionNode, EvalError> {
+        Ok(Expr::from(self).derivative_in(variable, angle_mode)?.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::{Context, Environment};
+    use crate::function::Arity;
+    use crate::parser::{parse, parse_expr, parse_expr_with};
+
+    fn derivative(source: &str) -> String {
+        parse_expr(source)
+            .unwrap()
+            .derivative("x")
+            .unwrap()
+            .to_string()
+    }
+
+    /// Compare the derivative with a central difference at a few points.
+    fn assert_slope(source: &str, env: &mut Environment) {
+        let expr = parse_expr_with(source, &*env).unwrap();
+        let derivative = expr.derivative_in("x", env.angle_mode()).unwrap();
+        for x in [0.2, 0.35, 0.5, 0.65] {
+            let h = 1e-6;
+            env.set("x", x + h);
+            let above = expr.evaluate_with(&*env).unwrap();
+            env.set("x", x - h);
+            let below = expr.evaluate_with(&*env).unwrap();
+            env.set("x", x);
+            let slope = derivative.evaluate_with(&*env).unwrap();
+            let expected = (above - below) / (2.0 * h);
+            assert!(
+                (slope - expected).abs() <= 1e-5 * expected.abs().max(1.0),
+                "d/dx {} = {} at {}: {} != {}",
+                source,
+                derivative,
+                x,
+                slope,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn derivative_polynomials() {
+        assert_eq!(derivative("7"), "0");
+        assert_eq!(derivative("y"), "0");
+        assert_eq!(derivative("x"), "1");
+        assert_eq!(derivative("x^2"), "2*x");
+        assert_eq!(derivative("x^3 + 2x^2 - x + 5"), "3*x^2+4*x-1");
+        assert_eq!(derivative("-x"), "-1");
+        assert_eq!(derivative("a*x + b"), "a");
+        assert_eq!(derivative("x/y"), "1/y");
+        assert_eq!(derivative("1/x"), "-1/x^2");
+        assert_eq!(derivative("(x+1)^2"), "2*(x+1)");
+        assert_eq!(derivative("-5"), "0");
+        assert_eq!(derivative("-sqrt 4"), "0");
+        assert_eq!(derivative("-(-y)"), "0");
+    }
+
+    #[test]
+    fn derivative_functions() {
+        assert_eq!(derivative("sin(x)"), "cos(x)");
+        assert_eq!(derivative("cos x"), "-sin(x)");
+        assert_eq!(derivative("tan(x)"), "sec(x)^2");
+        assert_eq!(derivative("sec(x)"), "sec(x)*tan(x)");
+        assert_eq!(derivative("csc(x)"), "-cosec(x)*cot(x)");
+        assert_eq!(derivative("cot(x)"), "-cosec(x)^2");
+        assert_eq!(derivative("e^x"), "e^x");
+        assert_eq!(derivative("exp(x^2)"), "2*exp(x^2)*x");
+        assert_eq!(derivative("sqrt(x)"), "0.5/sqrt(x)");
+        assert_eq!(derivative("ln(y)"), "0");
+    }
+
+    #[test]
+    fn derivative_matches_slope() {
+        let sources = [
+            "3x^2 - 2x + 1",
+            "x*(x-1)/(x+2)",
+            "x^x + 2^x + x^0.5",
+            "x % 0.3 + 1 % (x + 0.1)",
+            "-x°",
+            "sin(x) + cos(x) + tan(x)",
+            "sec(x) + cosec(x) + cot(x)",
+            "asin(x) + acos(x) + atan(x)",
+            "asec(x+1) + acsc(x+1) + acot(x)",
+            "sinh(x) + cosh(x) + tanh(x)",
+            "asinh(x) + acosh(x+1) + atanh(x)",
+            "exp(x) + ln(x) + log(x) + log2(x) + log10(x)",
+            "log(x+1, x) + log(2, x^2)",
+            "sqrt(x) + cbrt(x) + abs(x - 1)",
+            "atan2(x, 1 - x)",
+            "sin(cos(x^2)) * ln(1 + x)",
+        ];
+        for angle_mode in [AngleMode::Radians, AngleMode::Degrees, AngleMode::Gradians] {
+            let mut env = Environment::new();
+            env.set_angle_mode(angle_mode);
+            for source in sources {
+                assert_slope(source, &mut env);
+            }
+        }
+    }
+
+    #[test]
+    fn derivative_unknown_function() {
+        let mut env = Environment::new();
+        env.register_function("double", Arity::Exact(1), |args| Ok(2.0 * args[0]));
+        let derivative = |source: &str| parse_expr_with(source, &env).unwrap().derivative("x");
+        assert_eq!(
+            derivative("double(x)").unwrap_err().kind,
+            EvalErrorKind::NotDifferentiable(String::from("double"))
+        );
+        assert_eq!(
+            derivative("2 * double(x^2)").unwrap_err().kind,
+            EvalErrorKind::NotDifferentiable(String::from("double"))
+        );
+        assert_eq!(derivative("double(y) + x").unwrap().to_string(), "1");
+    }
+
+    #[test]
+    fn derivative_legacy_node() {
+        let node = parse("x^2").unwrap().derivative("x").unwrap();
+        assert_eq!(node.to_string(), "2*x");
+        assert_eq!(node.evaluate("x", 3.0).unwrap(), 6.0);
+    }
+}
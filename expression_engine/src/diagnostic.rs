@@ -0,0 +1,270 @@
+use crate::context::Context;
+use crate::function::Arity;
+use crate::token::{ErrorKind, Expected, ParseError, Span};
+use std::fmt;
+
+const OPERATORS: &str = "+-*/^%";
+
+///
+/// A parse error explained for people: a specific message, the location it refers to and,
+/// for misspelled function names, a suggestion.
+///
+/// Displaying it prints the expression with the location underlined.
+///
+/// # Examples
+///
+/// ```
+/// use expression_engine::{parse, Diagnostic};
+///
+/// let source = "sinn(x) + 1";
+/// let error = parse(source).err().unwrap();
+/// let diagnostic = Diagnostic::new(source, &error);
+///
+/// assert_eq!(diagnostic.message, "unknown function `sinn`");
+/// assert_eq!(diagnostic.help.as_deref(), Some("did you mean `sin`?"));
+/// assert_eq!(
+///     diagnostic.to_string(),
+///     "error: unknown function `sinn`\n  sinn(x) + 1\n  ^^^^\n  help: did you mean `sin`?"
+/// );
+/// ```
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub source: String,
+    pub message: String,
+    /// Location in `source` the message refers to, not always the span of the error.
+    pub span: Span,
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    /// Explain `error`, raised parsing `source`, suggesting the built-in functions.
+    pub fn new(source: &str, error: &ParseError) -> Self {
+        Self::with_context(source, error, &())
+    }
+
+    /// Explain `error`, raised parsing `source` with `ctx`, suggesting the functions of `ctx`.
+    pub fn with_context(source: &str, error: &ParseError, ctx: &(impl Context + ?Sized)) -> Self {
+        let mut span = error.span;
+        let mut help = None;
+        let found = match &source[span.start..span.end] {
+            "" => String::from("end of expression"),
+            text => format!("'{}'", text),
+        };
+
+        let message = match &error.kind {
+            ErrorKind::Empty => String::from("expression is empty"),
+            ErrorKind::InvalidNumberParsed => format!("invalid number {}", found),
+            ErrorKind::UnexpectedCharacter(character) => {
+                format!("unexpected character '{}'", character)
+            }
+            ErrorKind::UnknownFunction(name) => {
+                help = suggest(name, ctx.function_names())
+                    .map(|suggestion| format!("did you mean `{}`?", suggestion));
+                format!("unknown function `{}`", name)
+            }
+            ErrorKind::InvalidArgumentCount => {
+                let call = &source[span.start..span.end];
+                let name = call.split('(').next().unwrap_or(call).trim_end();
+                match ctx.function(name).map(|function| function.arity()) {
+                    Some(arity) => format!(
+                        "`{}` takes {} argument{}",
+                        name,
+                        arity,
+                        if arity == Arity::Exact(1) { "" } else { "s" }
+                    ),
+                    None => format!("wrong number of arguments to `{}`", name),
+                }
+            }
+            ErrorKind::UnclosedBracket(open) => format!("unclosed '{}' opened here", open),
+            ErrorKind::UnmatchedBracket(close) => format!("unmatched '{}'", close),
+            ErrorKind::MismatchedBracket { open, close } => {
+                format!("'{}' closed by '{}'", open, close)
+            }
+            ErrorKind::InvalidExpression | ErrorKind::InvalidOpenCloseParantheses => {
+                match (error.expected, preceding_operator(source, span)) {
+                    (Some(Expected::Operand), Some((operator, operator_span))) => {
+                        span = operator_span;
+                        format!("operator '{}' is missing its right operand", operator)
+                    }
+                    (Some(Expected::Operand), None) if is_binary_operator(&found) => {
+                        format!("operator {} is missing its left operand", found)
+                    }
+                    (Some(Expected::ClosingBracket(close)), _) if found == "','" => {
+                        help = Some(String::from(
+                            "only function calls take several arguments separated by ','",
+                        ));
+                        format!("expected '{}', found {}", close, found)
+                    }
+                    (Some(expected), _) => format!("expected {}, found {}", expected, found),
+                    (None, _) => format!("unexpected {}", found),
+                }
+            }
+        };
+
+        Self {
+            source: source.to_string(),
+            message,
+            span,
+            help,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let columns = self.span.char_range(&self.source);
+        writeln!(f, "error: {}", self.message)?;
+        writeln!(f, "  {}", self.source)?;
+        write!(
+            f,
+            "  {}{}",
+            " ".repeat(columns.start),
+            "^".repeat(columns.len().max(1))
+        )?;
+        if let Some(help) = &self.help {
+            write!(f, "\n  help: {}", help)?;
+        }
+        Ok(())
+    }
+}
+
+/// The operator directly before `span`, ignoring whitespace, with its own span.
+fn preceding_operator(source: &str, span: Span) -> Option<(char, Span)> {
+    let before = source[..span.start].trim_end();
+    let operator = before.chars().next_back()?;
+    if OPERATORS.contains(operator) {
+        let start = before.len() - operator.len_utf8();
+        Some((operator, Span::new(start, before.len())))
+    } else {
+        None
+    }
+}
+
+/// A quoted operator that cannot be read as a prefix sign.
+fn is_binary_operator(found: &str) -> bool {
+    matches!(found, "'*'" | "'/'" | "'^'" | "'%'")
+}
+
+///
+/// The name among `names` closest to `name`, if it is within a third of the length of `name`
+/// in edits, so `sinn` suggests `sin` but an unrelated name suggests nothing.
+///
+pub(crate) fn suggest<'n>(name: &str, names: impl IntoIterator<Item = &'n str>) -> Option<&'n str> {
+    let max_distance = (name.chars().count() / 3).max(1);
+    names
+        .into_iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance, the number of single character edits turning `a` into `b`.
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::Environment;
+    use crate::parser::{parse, parse_with};
+
+    fn diagnose(source: &str) -> Diagnostic {
+        Diagnostic::new(source, &parse(source).err().unwrap())
+    }
+
+    #[test]
+    fn diagnostic_messages() {
+        assert_eq!(diagnose("").message, "expression is empty");
+        assert_eq!(diagnose("2*(x+1").message, "unclosed '(' opened here");
+        assert_eq!(diagnose("2*(x+1").span, Span::new(2, 3));
+        assert_eq!(diagnose("x+1)").message, "unmatched ')'");
+        assert_eq!(diagnose("[x+1}").message, "'[' closed by '}'");
+        assert_eq!(diagnose("[x+1}").span, Span::new(4, 5));
+        assert_eq!(diagnose("{x").message, "unclosed '{' opened here");
+
+        let missing = diagnose("x + * 2");
+        assert_eq!(missing.message, "operator '+' is missing its right operand");
+        assert_eq!(missing.span, Span::new(2, 3));
+        assert_eq!(
+            diagnose("x^").message,
+            "operator '^' is missing its right operand"
+        );
+        assert_eq!(
+            diagnose("*x").message,
+            "operator '*' is missing its left operand"
+        );
+        assert_eq!(
+            diagnose("x, 2").message,
+            "expected an operator or the end of the expression, found ','"
+        );
+        assert_eq!(diagnose("(x, 2)").message, "expected ')', found ','");
+        assert_eq!(diagnose("atan2(x)").message, "`atan2` takes 2 arguments");
+        assert_eq!(diagnose("sqrt(x, 2)").message, "`sqrt` takes 1 argument");
+        assert_eq!(diagnose("x & 1").message, "unexpected character '&'");
+        assert_eq!(
+            diagnose("sin()").message,
+            "expected a number, variable, function or '(', found ')'"
+        );
+    }
+
+    #[test]
+    fn diagnostic_suggestions() {
+        assert_eq!(
+            diagnose("sinn(x)").help.as_deref(),
+            Some("did you mean `sin`?")
+        );
+        assert_eq!(
+            diagnose("sqr(x)").help.as_deref(),
+            Some("did you mean `sqrt`?")
+        );
+        assert_eq!(
+            diagnose("sinn (x)").help.as_deref(),
+            Some("did you mean `sin`?")
+        );
+
+        let mut env = Environment::new();
+        env.register_function("lerp", Arity::Exact(3), |args| Ok(args[0]));
+        let source = "lrp(1, 2, x)";
+        let error = parse_with(source, &env).err().unwrap();
+        assert_eq!(
+            Diagnostic::with_context(source, &error, &env)
+                .help
+                .as_deref(),
+            Some("did you mean `lerp`?")
+        );
+    }
+
+    #[test]
+    fn diagnostic_render() {
+        assert_eq!(
+            diagnose("θ + (x").to_string(),
+            "error: unclosed '(' opened here\n  θ + (x\n      ^"
+        );
+        assert_eq!(
+            diagnose("x+").to_string(),
+            "error: operator '+' is missing its right operand\n  x+\n   ^"
+        );
+    }
+
+    #[test]
+    fn suggest_closest() {
+        assert_eq!(edit_distance("sinn", "sin"), 1);
+        assert_eq!(edit_distance("cosh", "cos"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(suggest("tann", ["tan", "tanh"]), Some("tan"));
+        assert_eq!(suggest("xyz", ["sin", "cos"]), None);
+    }
+}
@@ -0,0 +1,197 @@
+use crate::function::Arity;
+use crate::node::{EvalError, EvalErrorKind};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElementaryFunction {
+    Exp,
+    Ln,
+    Log,
+    Log2,
+    Log10,
+    Sqrt,
+    Cbrt,
+    Abs,
+}
+
+impl ElementaryFunction {
+    pub const ALL: [ElementaryFunction; 8] = [
+        ElementaryFunction::Exp,
+        ElementaryFunction::Ln,
+        ElementaryFunction::Log,
+        ElementaryFunction::Log2,
+        ElementaryFunction::Log10,
+        ElementaryFunction::Sqrt,
+        ElementaryFunction::Cbrt,
+        ElementaryFunction::Abs,
+    ];
+
+    pub fn from(token_str: &str) -> Option<ElementaryFunction> {
+        match token_str {
+            "exp" => Some(ElementaryFunction::Exp),
+            "ln" => Some(ElementaryFunction::Ln),
+            "log" => Some(ElementaryFunction::Log),
+            "log2" => Some(ElementaryFunction::Log2),
+            "log10" => Some(ElementaryFunction::Log10),
+            "sqrt" => Some(ElementaryFunction::Sqrt),
+            "cbrt" => Some(ElementaryFunction::Cbrt),
+            "abs" => Some(ElementaryFunction::Abs),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ElementaryFunction::Exp => "exp",
+            ElementaryFunction::Ln => "ln",
+            ElementaryFunction::Log => "log",
+            ElementaryFunction::Log2 => "log2",
+            ElementaryFunction::Log10 => "log10",
+            ElementaryFunction::Sqrt => "sqrt",
+            ElementaryFunction::Cbrt => "cbrt",
+            ElementaryFunction::Abs => "abs",
+        }
+    }
+
+    /// `log` takes either one argument, the base 10 logarithm, or a base and an argument.
+    pub fn arity(&self) -> Arity {
+        match self {
+            ElementaryFunction::Log => Arity::Range(1, 2),
+            _ => Arity::Exact(1),
+        }
+    }
+
+    ///
+    /// Evaluate the function. Logarithms of non-positive values, logarithms in a base that
+    /// is not positive or is 1, and square roots of negative values fail with
+    /// [`EvalErrorKind::DomainError`] rather than producing NaN. A number of arguments the
+    /// function does not accept fails with [`EvalErrorKind::InvalidExpression`].
+    ///
+    pub fn evaluate(&self, args: &[f64]) -> Result<f64, EvalError> {
+        if !self.arity().accepts(args.len()) {
+            return Err(EvalError {
+                kind: EvalErrorKind::InvalidExpression,
+            });
+        }
+        let value = args[args.len() - 1];
+        match self {
+            ElementaryFunction::Exp => Ok(value.exp()),
+            ElementaryFunction::Ln => self.check(value, value > 0.0).map(f64::ln),
+            ElementaryFunction::Log => match args {
+                [base, _] => {
+                    self.check(*base, *base > 0.0 && *base != 1.0)?;
+                    self.check(value, value > 0.0).map(|value| value.log(*base))
+                }
+                _ => self.check(value, value > 0.0).map(f64::log10),
+            },
+            ElementaryFunction::Log2 => self.check(value, value > 0.0).map(f64::log2),
+            ElementaryFunction::Log10 => self.check(value, value > 0.0).map(f64::log10),
+            ElementaryFunction::Sqrt => self.check(value, value >= 0.0).map(f64::sqrt),
+            ElementaryFunction::Cbrt => Ok(value.cbrt()),
+            ElementaryFunction::Abs => Ok(value.abs()),
+        }
+    }
+
+    fn check(&self, value: f64, in_domain: bool) -> Result<f64, EvalError> {
+        if in_domain {
+            Ok(value)
+        } else {
+            Err(EvalError {
+                kind: EvalErrorKind::DomainError {
+                    function: self.name().to_string(),
+                    value,
+                },
+            })
+        }
+    }
+}
+
+impl fmt::Display for ElementaryFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f64 = 1e-12;
+
+    #[test]
+    fn evaluate_elementary() {
+        assert_eq!(ElementaryFunction::Exp.evaluate(&[0.0]).unwrap(), 1.0);
+        assert_eq!(ElementaryFunction::Ln.evaluate(&[1.0]).unwrap(), 0.0);
+        assert_eq!(ElementaryFunction::Log.evaluate(&[1000.0]).unwrap(), 3.0);
+        assert!((ElementaryFunction::Log.evaluate(&[2.0, 8.0]).unwrap() - 3.0).abs() < EPSILON);
+        assert_eq!(ElementaryFunction::Log2.evaluate(&[8.0]).unwrap(), 3.0);
+        assert_eq!(ElementaryFunction::Log10.evaluate(&[0.01]).unwrap(), -2.0);
+        assert_eq!(ElementaryFunction::Sqrt.evaluate(&[2.25]).unwrap(), 1.5);
+        assert_eq!(ElementaryFunction::Cbrt.evaluate(&[-27.0]).unwrap(), -3.0);
+        assert_eq!(ElementaryFunction::Abs.evaluate(&[-1.5]).unwrap(), 1.5);
+    }
+
+    #[test]
+    fn evaluate_elementary_domain() {
+        let domain_error = |function: &str, value: f64| EvalErrorKind::DomainError {
+            function: function.to_string(),
+            value,
+        };
+        let kind =
+            |function: ElementaryFunction, args: &[f64]| function.evaluate(args).unwrap_err().kind;
+
+        assert_eq!(
+            kind(ElementaryFunction::Ln, &[0.0]),
+            domain_error("ln", 0.0)
+        );
+        assert_eq!(
+            kind(ElementaryFunction::Log2, &[-1.0]),
+            domain_error("log2", -1.0)
+        );
+        assert_eq!(
+            kind(ElementaryFunction::Log10, &[-1.0]),
+            domain_error("log10", -1.0)
+        );
+        assert_eq!(
+            kind(ElementaryFunction::Log, &[1.0, 5.0]),
+            domain_error("log", 1.0)
+        );
+        assert_eq!(
+            kind(ElementaryFunction::Log, &[2.0, -5.0]),
+            domain_error("log", -5.0)
+        );
+        assert_eq!(
+            kind(ElementaryFunction::Sqrt, &[-4.0]),
+            domain_error("sqrt", -4.0)
+        );
+        assert!(ElementaryFunction::Sqrt.evaluate(&[0.0]).is_ok());
+    }
+
+    #[test]
+    fn evaluate_elementary_arity() {
+        let kind =
+            |function: ElementaryFunction, args: &[f64]| function.evaluate(args).unwrap_err().kind;
+        assert_eq!(
+            kind(ElementaryFunction::Exp, &[]),
+            EvalErrorKind::InvalidExpression
+        );
+        assert_eq!(
+            kind(ElementaryFunction::Sqrt, &[4.0, 9.0]),
+            EvalErrorKind::InvalidExpression
+        );
+        assert_eq!(
+            kind(ElementaryFunction::Log, &[2.0, 8.0, 1.0]),
+            EvalErrorKind::InvalidExpression
+        );
+    }
+
+    #[test]
+    fn elementary_from_name() {
+        for function in ElementaryFunction::ALL {
+            assert_eq!(ElementaryFunction::from(function.name()), Some(function));
+            assert_eq!(function.to_string(), function.name());
+        }
+        assert_eq!(ElementaryFunction::from("sin"), None);
+    }
+}
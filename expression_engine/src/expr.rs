@@ -0,0 +1,534 @@
+use crate::context::Context;
+use crate::node::{
+    apply_operator, call_function, check_finite, evaluate_trigonometric, EvalError, EvalErrorKind,
+    ExpressionNode, ExpressionNodeType,
+};
+use crate::operator::{Associativity, Operator};
+use crate::trace;
+use crate::trigonometric::TrigonometricFunction;
+use crate::visit::{Folder, Visitor};
+use std::fmt;
+
+/// Binding power of operands that never need parentheses.
+const ATOM_PRECEDENCE: u8 = u8::MAX;
+
+///
+/// Expression tree with one variant per construct, returned by [`crate::parse_expr`].
+///
+/// Unlike [`ExpressionNode`] every operation is explicit: implicit multiplication is a
+/// [`Expr::Binary`] with [`Operator::Multiply`] and trigonometric functions are
+/// [`Expr::Call`]s like any other function.
+///
+/// # Examples
+///
+/// ```
+/// use expression_engine::{parse_expr, Expr, Operator};
+///
+/// let expr = parse_expr("2x + sin(y)").unwrap();
+/// match &expr {
+///     Expr::Binary { op: Operator::Add, left, right } => {
+///         assert!(matches!(left.as_ref(), Expr::Binary { op: Operator::Multiply, .. }));
+///         assert!(matches!(right.as_ref(), Expr::Call { name, .. } if name == "sin"));
+///     }
+///     _ => unreachable!(),
+/// }
+/// assert_eq!(expr.to_string(), "2*x+sin(y)");
+/// ```
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// A number, with its spelling if written other than its value prints, such as `0xFF`.
+    Number {
+        value: f64,
+        literal: Option<String>,
+    },
+    Variable(String),
+    Constant {
+        name: String,
+        value: f64,
+    },
+    Unary {
+        op: UnaryOperator,
+        operand: Box<Expr>,
+    },
+    Binary {
+        op: Operator,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    Call {
+        name: String,
+        args: Vec<Expr>,
+    },
+}
+
+/// Operator applying to a single operand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOperator {
+    /// Prefix `-`.
+    Negate,
+    /// Postfix `°`, an angle in degrees converted to the context's angle mode.
+    Degrees,
+}
+
+impl Expr {
+    ///
+    /// Evaluate the expression with `variable` bound to `substitute`.
+    /// Any other variable in the expression fails with [`EvalErrorKind::NoSubstitute`].
+    ///
+    pub fn evaluate(&self, variable: &str, substitute: f64) -> Result<f64, EvalError> {
+        self.evaluate_with(&(variable, substitute))
+    }
+
+    ///
+    /// Evaluate the expression, looking variables up in `ctx`, with the same results as
+    /// [`ExpressionNode::evaluate_with`].
+    ///
+    pub fn evaluate_with(&self, ctx: &(impl Context + ?Sized)) -> Result<f64, EvalError> {
+        let result = self.evaluate_expr(ctx);
+        trace::evaluated(self, &result);
+        result
+    }
+
+    /// Walk the expression with `visitor`, starting at the root.
+    pub fn visit(&self, visitor: &mut (impl Visitor + ?Sized)) {
+        visitor.visit_expr(self)
+    }
+
+    /// Transform the expression with `folder`, starting at the root.
+    pub fn fold(self, folder: &mut (impl Folder + ?Sized)) -> Expr {
+        folder.fold_expr(self)
+    }
+
+    fn evaluate_expr(&self, ctx: &(impl Context + ?Sized)) -> Result<f64, EvalError> {
+        let value = match self {
+            Expr::Number { value, .. } | Expr::Constant { value, .. } => *value,
+            Expr::Variable(name) => ctx.variable(name).ok_or_else(|| EvalError {
+                kind: EvalErrorKind::NoSubstitute(name.clone()),
+            })?,
+            Expr::Unary { op, operand } => {
+                let value = operand.evaluate_expr(ctx)?;
+                match op {
+                    UnaryOperator::Negate => -value,
+                    UnaryOperator::Degrees => ctx.angle_mode().from_degrees(value),
+                }
+            }
+            Expr::Binary { op, left, right } => apply_operator(
+                ctx,
+                *op,
+                left.evaluate_expr(ctx)?,
+                right.evaluate_expr(ctx)?,
+                self,
+            )?,
+            Expr::Call { name, args } => match (TrigonometricFunction::from(name), &args[..]) {
+                (Some(trig_fn), [arg]) => {
+                    evaluate_trigonometric(ctx, &trig_fn, arg.evaluate_expr(ctx)?)?
+                }
+                _ => call_function(ctx, name, args, |arg| arg.evaluate_expr(ctx))?,
+            },
+        };
+        check_finite(ctx, value, self)
+    }
+
+    /// Binding power of the expression as an operand, deciding where parentheses are needed.
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Binary { op, .. } => op.precedence(),
+            Expr::Unary {
+                op: UnaryOperator::Negate,
+                ..
+            } => Operator::PREFIX_PRECEDENCE,
+            Expr::Number {
+                value,
+                literal: None,
+            } if value.is_sign_negative() => Operator::PREFIX_PRECEDENCE,
+            _ => ATOM_PRECEDENCE,
+        }
+    }
+
+    /// Write the expression, in parentheses if it binds looser than `precedence`.
+    fn fmt_operand(&self, f: &mut fmt::Formatter<'_>, precedence: u8) -> fmt::Result {
+        if self.precedence() < precedence {
+            write!(f, "({})", self)
+        } else {
+            write!(f, "{}", self)
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Number {
+                literal: Some(literal),
+                ..
+            } => write!(f, "{}", literal),
+            Expr::Number { value, .. } => write!(f, "{}", value),
+            Expr::Variable(name) | Expr::Constant { name, .. } => write!(f, "{}", name),
+            Expr::Unary {
+                op: UnaryOperator::Negate,
+                operand,
+            } => {
+                write!(f, "-")?;
+                operand.fmt_operand(f, Operator::PREFIX_PRECEDENCE)
+            }
+            Expr::Unary {
+                op: UnaryOperator::Degrees,
+                operand,
+            } => {
+                operand.fmt_operand(f, ATOM_PRECEDENCE)?;
+                write!(f, "°")
+            }
+            Expr::Binary { op, left, right } => {
+                let precedence = op.precedence();
+                let (left_precedence, right_precedence) = match op.associativity() {
+                    Associativity::Left => (precedence, precedence + 1),
+                    Associativity::Right => (precedence + 1, precedence),
+                };
+                left.fmt_operand(f, left_precedence)?;
+                write!(f, "{}", op)?;
+                right.fmt_operand(f, right_precedence)
+            }
+            Expr::Call { name, args } => {
+                write!(f, "{}(", name)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl From<&ExpressionNode> for Expr {
+    fn from(node: &ExpressionNode) -> Self {
+        let left = Expr::from(&node.left);
+        match &node.right {
+            // a right operand without operator is an implicit multiplication
+            Some(right) => Expr::Binary {
+                op: node.operator.unwrap_or(Operator::Multiply),
+                left: Box::new(left),
+                right: Box::new(Expr::from(right)),
+            },
+            None => left,
+        }
+    }
+}
+
+impl From<ExpressionNode> for Expr {
+    fn from(node: ExpressionNode) -> Self {
+        Expr::from(&node)
+    }
+}
+
+impl From<&ExpressionNodeType> for Expr {
+    fn from(operand: &ExpressionNodeType) -> Self {
+        match operand {
+            ExpressionNodeType::ValueOperand(value) => Expr::Number {
+                value: *value,
+                literal: None,
+            },
+            ExpressionNodeType::LiteralOperand((literal, value)) => Expr::Number {
+                value: *value,
+                literal: Some(literal.clone()),
+            },
+            ExpressionNodeType::VariableOperand(name) => Expr::Variable(name.clone()),
+            ExpressionNodeType::ConstantOperand((name, value)) => Expr::Constant {
+                name: name.clone(),
+                value: *value,
+            },
+            ExpressionNodeType::TrigonometricOperand((trig_fn, node)) => Expr::Call {
+                name: trig_fn.name().to_string(),
+                args: vec![Expr::from(node.as_ref())],
+            },
+            ExpressionNodeType::NegateOperand(node) => Expr::Unary {
+                op: UnaryOperator::Negate,
+                operand: Box::new(Expr::from(node.as_ref())),
+            },
+            ExpressionNodeType::DegreesOperand(node) => Expr::Unary {
+                op: UnaryOperator::Degrees,
+                operand: Box::new(Expr::from(node.as_ref())),
+            },
+            ExpressionNodeType::FunctionCall { name, args } => Expr::Call {
+                name: name.clone(),
+                args: args.iter().map(Expr::from).collect(),
+            },
+            ExpressionNodeType::NodeOperand(node) => Expr::from(node.as_ref()),
+        }
+    }
+}
+
+impl From<&Expr> for ExpressionNode {
+    fn from(expr: &Expr) -> Self {
+        match expr {
+            Expr::Binary { op, left, right } => ExpressionNode::new(
+                ExpressionNode::from(left.as_ref()).into_operand(),
+                Some(*op),
+                Some(ExpressionNode::from(right.as_ref()).into_operand()),
+            ),
+            _ => ExpressionNode::new(ExpressionNodeType::from(expr), None, None),
+        }
+    }
+}
+
+impl From<Expr> for ExpressionNode {
+    fn from(expr: Expr) -> Self {
+        ExpressionNode::from(&expr)
+    }
+}
+
+impl From<&Expr> for ExpressionNodeType {
+    fn from(expr: &Expr) -> Self {
+        let boxed = |expr: &Expr| Box::new(ExpressionNode::from(expr));
+        match expr {
+            // a negative value is a negation, so it prints as one wherever it is an operand
+            Expr::Number {
+                value,
+                literal: None,
+            } if *value < 0.0 => ExpressionNodeType::NegateOperand(Box::new(ExpressionNode::new(
+                ExpressionNodeType::ValueOperand(-value),
+                None,
+                None,
+            ))),
+            Expr::Number {
+                value,
+                literal: None,
+            } => ExpressionNodeType::ValueOperand(*value),
+            Expr::Number {
+                value,
+                literal: Some(literal),
+            } => ExpressionNodeType::LiteralOperand((literal.clone(), *value)),
+            Expr::Variable(name) => ExpressionNodeType::VariableOperand(name.clone()),
+            Expr::Constant { name, value } => {
+                ExpressionNodeType::ConstantOperand((name.clone(), *value))
+            }
+            Expr::Unary {
+                op: UnaryOperator::Negate,
+                operand,
+            } => ExpressionNodeType::NegateOperand(boxed(operand)),
+            Expr::Unary {
+                op: UnaryOperator::Degrees,
+                operand,
+            } => ExpressionNodeType::DegreesOperand(boxed(operand)),
+            Expr::Binary { .. } => ExpressionNode::from(expr).into_operand(),
+            Expr::Call { name, args } => match (TrigonometricFunction::from(name), &args[..]) {
+                (Some(trig_fn), [arg]) => {
+                    ExpressionNodeType::TrigonometricOperand((trig_fn, boxed(arg)))
+                }
+                _ => ExpressionNodeType::FunctionCall {
+                    name: name.clone(),
+                    args: args.iter().map(ExpressionNode::from).collect(),
+                },
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::Environment;
+    use crate::node::EvalMode;
+    use crate::parser::{parse, parse_expr};
+    use crate::trigonometric::AngleMode;
+    use std::collections::HashMap;
+
+    fn number(value: f64) -> Box<Expr> {
+        Box::new(Expr::Number {
+            value,
+            literal: None,
+        })
+    }
+
+    fn variable(name: &str) -> Box<Expr> {
+        Box::new(Expr::Variable(name.to_string()))
+    }
+
+    #[test]
+    fn parse_expr_structure() {
+        assert_eq!(
+            parse_expr("3x - 1").unwrap(),
+            Expr::Binary {
+                op: Operator::Substract,
+                left: Box::new(Expr::Binary {
+                    op: Operator::Multiply,
+                    left: number(3.0),
+                    right: variable("x"),
+                }),
+                right: number(1.0),
+            }
+        );
+        assert_eq!(
+            parse_expr("-x^2").unwrap(),
+            Expr::Unary {
+                op: UnaryOperator::Negate,
+                operand: Box::new(Expr::Binary {
+                    op: Operator::Power,
+                    left: variable("x"),
+                    right: number(2.0),
+                }),
+            }
+        );
+        assert_eq!(
+            parse_expr("csc(30°)").unwrap(),
+            Expr::Call {
+                name: String::from("cosec"),
+                args: vec![Expr::Unary {
+                    op: UnaryOperator::Degrees,
+                    operand: number(30.0),
+                }],
+            }
+        );
+        assert_eq!(
+            parse_expr("log(2, 0x10) * pi").unwrap(),
+            Expr::Binary {
+                op: Operator::Multiply,
+                left: Box::new(Expr::Call {
+                    name: String::from("log"),
+                    args: vec![
+                        Expr::Number {
+                            value: 2.0,
+                            literal: None,
+                        },
+                        Expr::Number {
+                            value: 16.0,
+                            literal: Some(String::from("0x10")),
+                        },
+                    ],
+                }),
+                right: Box::new(Expr::Constant {
+                    name: String::from("pi"),
+                    value: std::f64::consts::PI,
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn display_minimal_parentheses() {
+        let cases = [
+            ("x - (y - z)", "x-(y-z)"),
+            ("(x - y) - z", "x-y-z"),
+            ("(x^y)^z", "(x^y)^z"),
+            ("x^(y^z)", "x^y^z"),
+            ("(x + 1)(x - 1)", "(x+1)*(x-1)"),
+            ("-(x + 1)", "-(x+1)"),
+            ("(-x)^2", "(-x)^2"),
+            ("2^-x", "2^(-x)"),
+            ("(x + 1)°", "(x+1)°"),
+            ("atan2(y, x + 1)", "atan2(y,x+1)"),
+            ("1_000 + 1e3", "1_000+1e3"),
+        ];
+        for (source, expected) in cases {
+            let expr = parse_expr(source).unwrap();
+            assert_eq!(expr.to_string(), expected);
+            assert_eq!(parse_expr(expected).unwrap(), expr);
+        }
+    }
+
+    #[test]
+    fn evaluate_like_legacy() {
+        let mut env = Environment::new();
+        env.set("x", 0.5);
+        env.set("y", 2.0);
+        env.set_angle_mode(AngleMode::Degrees);
+        let sources = [
+            "3x^2 - 2x + 1",
+            "-x^y % 0.3",
+            "sin(30°) + cos 60",
+            "asin x + atan2(y, x)",
+            "log(y, 8) / ln(e) + sqrt(y)",
+            "[x + 1]{y - 1}",
+        ];
+        for source in sources {
+            let legacy = parse(source).unwrap();
+            let expr = Expr::from(&legacy);
+            assert_eq!(
+                expr.evaluate_with(&env).unwrap(),
+                legacy.evaluate_with(&env).unwrap()
+            );
+            assert_eq!(
+                ExpressionNode::from(&expr).evaluate_with(&env).unwrap(),
+                legacy.evaluate_with(&env).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn evaluate_errors() {
+        let expr = parse_expr("x + y").unwrap();
+        assert_eq!(
+            expr.evaluate_with(&HashMap::from([("x", 1.0)]))
+                .err()
+                .unwrap()
+                .kind,
+            EvalErrorKind::NoSubstitute(String::from("y"))
+        );
+        assert_eq!(
+            expr.evaluate("x", 1.0).err().unwrap().kind,
+            EvalErrorKind::NoSubstitute(String::from("y"))
+        );
+        let call = Expr::Call {
+            name: String::from("nope"),
+            args: vec![],
+        };
+        assert_eq!(
+            call.evaluate("x", 1.0).err().unwrap().kind,
+            EvalErrorKind::UnknownFunction(String::from("nope"))
+        );
+    }
+
+    #[test]
+    fn evaluate_strict() {
+        let mut env = Environment::new();
+        env.set("x", 0.0);
+        env.set_eval_mode(EvalMode::Strict);
+        let kind = |source: &str| {
+            parse_expr(source)
+                .unwrap()
+                .evaluate_with(&env)
+                .err()
+                .unwrap()
+                .kind
+        };
+
+        assert_eq!(
+            kind("2 + 1/(x*3)"),
+            EvalErrorKind::DivisionByZero {
+                expression: String::from("1/(x*3)")
+            }
+        );
+        assert_eq!(
+            kind("cot(x) + 1"),
+            EvalErrorKind::DomainError {
+                function: String::from("cot"),
+                value: 0.0
+            }
+        );
+        assert_eq!(
+            kind("exp(1000) - 1"),
+            EvalErrorKind::NonFinite {
+                expression: String::from("exp(1000)")
+            }
+        );
+        assert_eq!(
+            parse_expr("1/(x+1)").unwrap().evaluate_with(&env).unwrap(),
+            1.0
+        );
+        assert_eq!(
+            parse_expr("1/x").unwrap().evaluate("x", 0.0).unwrap(),
+            f64::INFINITY
+        );
+    }
+
+    #[test]
+    fn convert_to_legacy() {
+        let expr = parse_expr("2(x + 1)^2 - sin x").unwrap();
+        let node = ExpressionNode::from(expr.clone());
+        assert_eq!(node.to_string(), "(2*((x+1)^2))-sin(x)");
+        assert_eq!(Expr::from(node), expr);
+    }
+}
@@ -0,0 +1,244 @@
+use crate::elementary::ElementaryFunction;
+use crate::node::EvalError;
+use crate::trigonometric::TrigonometricFunction;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+
+/// Callback computing a function value from its evaluated arguments.
+pub type FunctionCallback = dyn Fn(&[f64]) -> Result<f64, EvalError> + Send + Sync;
+
+/// Number of arguments a function accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arity {
+    Exact(usize),
+    AtLeast(usize),
+    /// Between the two bounds, inclusive.
+    Range(usize, usize),
+}
+
+impl Arity {
+    pub fn accepts(&self, count: usize) -> bool {
+        match self {
+            Arity::Exact(n) => count == *n,
+            Arity::AtLeast(n) => count >= *n,
+            Arity::Range(min, max) => (*min..=*max).contains(&count),
+        }
+    }
+}
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Arity::Exact(n) => write!(f, "{}", n)?,
+            Arity::AtLeast(n) => write!(f, "at least {}", n)?,
+            Arity::Range(min, max) => write!(f, "{} to {}", min, max)?,
+        }
+        Ok(())
+    }
+}
+
+/// A callable function together with the number of arguments it accepts.
+#[derive(Clone)]
+pub struct Function {
+    arity: Arity,
+    callback: Arc<FunctionCallback>,
+    returns_angle: bool,
+}
+
+impl Function {
+    pub fn new(
+        arity: Arity,
+        callback: impl Fn(&[f64]) -> Result<f64, EvalError> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            arity,
+            callback: Arc::new(callback),
+            returns_angle: false,
+        }
+    }
+
+    ///
+    /// Mark the function as returning an angle in radians, such as `atan2`.
+    /// Evaluation converts its result to the [`crate::AngleMode`] of the context.
+    ///
+    pub fn returning_angle(mut self) -> Self {
+        self.returns_angle = true;
+        self
+    }
+
+    pub fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    pub fn returns_angle(&self) -> bool {
+        self.returns_angle
+    }
+
+    /// Call the function, the caller is responsible for passing an accepted number of arguments.
+    pub fn call(&self, args: &[f64]) -> Result<f64, EvalError> {
+        (self.callback)(args)
+    }
+}
+
+impl fmt::Debug for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Function")
+            .field("arity", &self.arity)
+            .field("returns_angle", &self.returns_angle)
+            .finish_non_exhaustive()
+    }
+}
+
+///
+/// Functions callable from expressions, by name.
+///
+/// A new registry holds the built-in functions; register more to make them available
+/// to [`crate::parse_with`] and evaluation through an [`crate::Environment`].
+///
+/// # Examples
+///
+/// ```
+/// use expression_engine::{parse_with, Arity, Environment};
+///
+/// let mut env = Environment::new();
+/// env.register_function("clamp", Arity::Exact(3), |args| {
+///     Ok(args[0].max(args[1]).min(args[2]))
+/// });
+///
+/// let exp = parse_with("clamp(2x, 0, 1)", &env).unwrap();
+/// env.set("x", 0.75);
+/// assert_eq!(exp.evaluate_with(&env).unwrap(), 1.0);
+/// assert_eq!(exp.to_string(), "clamp((2*x),0,1)");
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct FunctionRegistry {
+    functions: HashMap<String, Function>,
+}
+
+impl FunctionRegistry {
+    pub fn new() -> Self {
+        let mut registry = Self {
+            functions: HashMap::new(),
+        };
+        for trig_fn in TrigonometricFunction::ALL {
+            registry.register(trig_fn.name(), Arity::Exact(1), move |args| {
+                Ok(trig_fn.evaluate(args[0]))
+            });
+        }
+        for (alias, trig_fn) in TrigonometricFunction::ALIASES {
+            registry.register(alias, Arity::Exact(1), move |args| {
+                Ok(trig_fn.evaluate(args[0]))
+            });
+        }
+        registry.insert(
+            "atan2",
+            Function::new(Arity::Exact(2), |args| Ok(args[0].atan2(args[1]))).returning_angle(),
+        );
+        for function in ElementaryFunction::ALL {
+            registry.register(function.name(), function.arity(), move |args| {
+                function.evaluate(args)
+            });
+        }
+        registry
+    }
+
+    /// Registry with the built-in functions, shared by every context that registers none.
+    pub fn builtin() -> &'static FunctionRegistry {
+        static BUILTIN: OnceLock<FunctionRegistry> = OnceLock::new();
+        BUILTIN.get_or_init(FunctionRegistry::new)
+    }
+
+    ///
+    /// Register `callback` under `name`, replacing any function of the same name.
+    /// Trigonometric names keep parsing to [`crate::ExpressionNodeType::TrigonometricOperand`].
+    ///
+    pub fn register(
+        &mut self,
+        name: &str,
+        arity: Arity,
+        callback: impl Fn(&[f64]) -> Result<f64, EvalError> + Send + Sync + 'static,
+    ) {
+        self.insert(name, Function::new(arity, callback));
+    }
+
+    /// Register `function` under `name`, replacing any function of the same name.
+    pub fn insert(&mut self, name: &str, function: Function) {
+        self.functions.insert(name.to_string(), function);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Function> {
+        self.functions.remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Function> {
+        self.functions.get(name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.functions.contains_key(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.functions.keys().map(String::as_str)
+    }
+}
+
+impl Default for FunctionRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arity_accepts() {
+        assert!(Arity::Exact(2).accepts(2));
+        assert!(!Arity::Exact(2).accepts(1));
+        assert!(Arity::AtLeast(1).accepts(3));
+        assert!(!Arity::AtLeast(1).accepts(0));
+        assert!(Arity::Range(1, 2).accepts(1));
+        assert!(Arity::Range(1, 2).accepts(2));
+        assert!(!Arity::Range(1, 2).accepts(3));
+    }
+
+    #[test]
+    fn registry_builtin() {
+        let registry = FunctionRegistry::builtin();
+        for name in [
+            "sin", "cos", "tan", "sec", "cosec", "csc", "cot", "asin", "acos", "atan", "asec",
+            "acsc", "acot", "atan2", "sinh", "cosh", "tanh", "asinh", "acosh", "atanh", "exp",
+            "ln", "log", "log2", "log10", "sqrt", "cbrt", "abs",
+        ] {
+            assert!(registry.contains(name));
+        }
+        let sin = registry.get("sin").unwrap();
+        assert_eq!(sin.arity(), Arity::Exact(1));
+        assert_eq!(sin.call(&[0.0]).unwrap(), 0.0);
+        assert!(!sin.returns_angle());
+        assert!(registry.get("atan2").unwrap().returns_angle());
+    }
+
+    #[test]
+    fn registry_register() {
+        let mut registry = FunctionRegistry::new();
+        registry.register("lerp", Arity::Exact(3), |args| {
+            Ok(args[0] + (args[1] - args[0]) * args[2])
+        });
+        assert_eq!(
+            registry
+                .get("lerp")
+                .unwrap()
+                .call(&[1.0, 3.0, 0.5])
+                .unwrap(),
+            2.0
+        );
+        assert!(registry.remove("lerp").is_some());
+        assert!(!registry.contains("lerp"));
+        assert!(!FunctionRegistry::builtin().contains("lerp"));
+    }
+}
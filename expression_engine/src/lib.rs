@@ -1,11 +1,34 @@
+mod analysis;
+mod batch;
+mod compile;
+mod constant;
+mod context;
+mod derivative;
+mod diagnostic;
+mod elementary;
+mod expr;
+mod function;
 mod node;
 mod operator;
 mod parser;
+mod simplify;
 mod token;
+mod trace;
 mod trigonometric;
+mod visit;
 
+pub use batch::BatchErrors;
+pub use compile::CompiledExpr;
+pub use context::{Context, Environment};
+pub use diagnostic::Diagnostic;
+pub use elementary::ElementaryFunction;
+pub use expr::{Expr, UnaryOperator};
+pub use function::{Arity, Function, FunctionCallback, FunctionRegistry};
 pub use node::ExpressionNode;
 pub use node::ExpressionNodeType;
-pub use operator::Operator;
-pub use parser::parse;
-pub use token::{ErrorKind, ParseError, Token};
+pub use node::{EvalError, EvalErrorKind, EvalMode};
+pub use operator::{Associativity, Operator};
+pub use parser::{parse, parse_expr, parse_expr_with, parse_with};
+pub use token::{ErrorKind, Expected, ParseError, Span, Token};
+pub use trigonometric::{AngleMode, TrigonometricFunction};
+pub use visit::{fold_expr, walk_expr, Folder, Visitor};
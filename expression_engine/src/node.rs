@@ -1,52 +1,226 @@
+use crate::context::Context;
+use crate::expr::Expr;
 use crate::operator::Operator;
+use crate::trace;
 use crate::trigonometric::TrigonometricFunction;
+use crate::visit::{Folder, Visitor};
 use std::fmt;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct EvalError {
     pub kind: EvalErrorKind,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum EvalErrorKind {
-    NoSubstitute,
+    /// No value was bound to the named variable.
+    NoSubstitute(String),
+    /// No function of that name is callable from the context.
+    UnknownFunction(String),
+    /// The function is not defined for the value, such as `ln` of a negative number or,
+    /// in [`EvalMode::Strict`], `asin(2)` and `tan` at a pole.
+    DomainError {
+        function: String,
+        value: f64,
+    },
+    /// In [`EvalMode::Strict`], the sub-expression divides by zero or raises zero to a
+    /// negative power.
+    DivisionByZero {
+        expression: String,
+    },
+    /// In [`EvalMode::Strict`], the sub-expression evaluated to infinity or NaN.
+    NonFinite {
+        expression: String,
+    },
+    /// The derivative of the named function is not known, such as one registered on an
+    /// [`crate::Environment`].
+    NotDifferentiable(String),
     InvalidOpenCloseParantheses,
     InvalidNumberParsed,
     InvalidExpression,
 }
 
+/// How evaluation treats results that are not real numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EvalMode {
+    /// Follow IEEE 754, so `1/0` is infinity and `asin(2)` NaN, as graphing wants. The
+    /// logarithms and `sqrt` are the exception: outside their domain they fail with
+    /// [`EvalErrorKind::DomainError`] in either mode.
+    #[default]
+    Ieee,
+    /// Fail with [`EvalErrorKind::DivisionByZero`], [`EvalErrorKind::DomainError`] or
+    /// [`EvalErrorKind::NonFinite`] naming the innermost offending sub-expression.
+    Strict,
+}
+
 #[derive(Clone)]
 pub enum ExpressionNodeType {
     ValueOperand(f64),
-    VariableOperand(char),
+    /// A number literal spelled other than its value prints, such as `0xFF` or `1e-3`.
+    LiteralOperand((String, f64)),
+    VariableOperand(String),
+    ConstantOperand((String, f64)),
     TrigonometricOperand((TrigonometricFunction, Box<ExpressionNode>)),
+    NegateOperand(Box<ExpressionNode>),
+    /// An angle written in degrees with a `°` suffix, converted to the context's angle mode.
+    DegreesOperand(Box<ExpressionNode>),
+    FunctionCall {
+        name: String,
+        args: Vec<ExpressionNode>,
+    },
     NodeOperand(Box<ExpressionNode>),
 }
 
 impl ExpressionNodeType {
-    fn evaluate(&self, variable: &str, substitute: f64) -> Result<f64, EvalError> {
-        Ok(match &self {
+    fn evaluate_with(&self, ctx: &(impl Context + ?Sized)) -> Result<f64, EvalError> {
+        let value = match &self {
             ExpressionNodeType::ValueOperand(value) => *value,
-            ExpressionNodeType::VariableOperand(variable) => substitute, //TODO the validation to check if it's exact variable substitution
+            ExpressionNodeType::VariableOperand(variable) => match ctx.variable(variable) {
+                Some(value) => value,
+                None => {
+                    return Err(EvalError {
+                        kind: EvalErrorKind::NoSubstitute(variable.clone()),
+                    })
+                }
+            },
+            ExpressionNodeType::LiteralOperand((_, value)) => *value,
+            ExpressionNodeType::ConstantOperand((_, value)) => *value,
             ExpressionNodeType::NodeOperand(expression_node) => {
-                expression_node.as_ref().evaluate(variable, substitute)?
+                expression_node.as_ref().evaluate_node(ctx)?
             }
             ExpressionNodeType::TrigonometricOperand((trig_fn, expression_node)) => {
-                trig_fn.evaluate(expression_node.as_ref().evaluate(variable, substitute)?)
+                evaluate_trigonometric(ctx, trig_fn, expression_node.as_ref().evaluate_node(ctx)?)?
+            }
+            ExpressionNodeType::NegateOperand(expression_node) => {
+                -expression_node.as_ref().evaluate_node(ctx)?
+            }
+            ExpressionNodeType::DegreesOperand(expression_node) => ctx
+                .angle_mode()
+                .from_degrees(expression_node.as_ref().evaluate_node(ctx)?),
+            ExpressionNodeType::FunctionCall { name, args } => {
+                call_function(ctx, name, args, |arg| arg.evaluate_node(ctx))?
             }
-        })
+        };
+        check_finite(ctx, value, self)
+    }
+}
+
+///
+/// Call the function `name` of `ctx` with `args`, each evaluated by `evaluate`, converting
+/// an angle result to the angle mode of `ctx`.
+///
+pub(crate) fn call_function<A>(
+    ctx: &(impl Context + ?Sized),
+    name: &str,
+    args: &[A],
+    evaluate: impl Fn(&A) -> Result<f64, EvalError>,
+) -> Result<f64, EvalError> {
+    let function = ctx.function(name).ok_or_else(|| EvalError {
+        kind: EvalErrorKind::UnknownFunction(name.to_string()),
+    })?;
+    if !function.arity().accepts(args.len()) {
+        return Err(EvalError {
+            kind: EvalErrorKind::InvalidExpression,
+        });
+    }
+    let values = args.iter().map(evaluate).collect::<Result<Vec<f64>, _>>()?;
+    let value = function.call(&values)?;
+    Ok(if function.returns_angle() {
+        ctx.angle_mode().from_radians(value)
+    } else {
+        value
+    })
+}
+
+///
+/// Apply `operator` to the evaluated operands of `expression`, in [`EvalMode::Strict`]
+/// failing on a zero divisor.
+///
+pub(crate) fn apply_operator(
+    ctx: &(impl Context + ?Sized),
+    operator: Operator,
+    left: f64,
+    right: f64,
+    expression: &dyn fmt::Display,
+) -> Result<f64, EvalError> {
+    let divides_by_zero = match operator {
+        Operator::Divide | Operator::Modulus => right == 0.0,
+        Operator::Power => left == 0.0 && right < 0.0,
+        _ => false,
+    };
+    if divides_by_zero && ctx.eval_mode() == EvalMode::Strict {
+        return Err(EvalError {
+            kind: EvalErrorKind::DivisionByZero {
+                expression: expression.to_string(),
+            },
+        });
+    }
+    Ok(operator.apply(left, right))
+}
+
+/// Evaluate `trig_fn` in the angle mode of `ctx`, in [`EvalMode::Strict`] checking its domain.
+pub(crate) fn evaluate_trigonometric(
+    ctx: &(impl Context + ?Sized),
+    trig_fn: &TrigonometricFunction,
+    value: f64,
+) -> Result<f64, EvalError> {
+    match ctx.eval_mode() {
+        EvalMode::Ieee => Ok(trig_fn.evaluate_in(value, ctx.angle_mode())),
+        EvalMode::Strict => trig_fn.evaluate_checked(value, ctx.angle_mode()),
     }
 }
 
+/// Pass `value` of `expression` on, in [`EvalMode::Strict`] failing if it is not finite.
+pub(crate) fn check_finite(
+    ctx: &(impl Context + ?Sized),
+    value: f64,
+    expression: &dyn fmt::Display,
+) -> Result<f64, EvalError> {
+    if !value.is_finite() && ctx.eval_mode() == EvalMode::Strict {
+        return Err(EvalError {
+            kind: EvalErrorKind::NonFinite {
+                expression: expression.to_string(),
+            },
+        });
+    }
+    Ok(value)
+}
+
 impl fmt::Display for ExpressionNodeType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match &self {
             ExpressionNodeType::ValueOperand(value) => write!(f, "{}", value)?,
             ExpressionNodeType::NodeOperand(node) => write!(f, "({})", node.as_ref())?,
             ExpressionNodeType::VariableOperand(var) => write!(f, "{}", var)?,
+            ExpressionNodeType::LiteralOperand((literal, _)) => write!(f, "{}", literal)?,
+            ExpressionNodeType::ConstantOperand((name, _)) => write!(f, "{}", name)?,
             ExpressionNodeType::TrigonometricOperand((trig_fn, node)) => {
                 write!(f, "{}({})", trig_fn, node.as_ref())?
             }
+            ExpressionNodeType::NegateOperand(node) => {
+                if node.is_leaf() {
+                    write!(f, "-{}", node.as_ref())?
+                } else {
+                    write!(f, "-({})", node.as_ref())?
+                }
+            }
+            ExpressionNodeType::DegreesOperand(node) => {
+                if node.is_leaf() {
+                    write!(f, "{}°", node.as_ref())?
+                } else {
+                    write!(f, "({})°", node.as_ref())?
+                }
+            }
+            ExpressionNodeType::FunctionCall { name, args } => {
+                write!(f, "{}(", name)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, ")")?
+            }
         }
         Ok(())
     }
@@ -62,39 +236,79 @@ pub struct ExpressionNode {
 impl ExpressionNode {
     pub fn generate_expression(&self) -> String {
         let mut expression_str = String::new();
-        expression_str.push_str(&self.left.to_string());
+        // `-x^2` reads as `-(x^2)`, so a negative base of `^` needs its own parentheses
+        let negative = match &self.left {
+            ExpressionNodeType::NegateOperand(_) => true,
+            ExpressionNodeType::ValueOperand(value) => value.is_sign_negative(),
+            _ => false,
+        };
+        if negative && self.operator == Some(Operator::Power) {
+            expression_str.push_str(&format!("({})", self.left));
+        } else {
+            expression_str.push_str(&self.left.to_string());
+        }
 
-        match &self.operator {
-            Some(op) => expression_str.push_str(&op.to_string()),
-            None => (),
+        if let Some(op) = &self.operator {
+            expression_str.push_str(&op.to_string());
         }
 
-        match &self.right {
-            Some(node) => expression_str.push_str(&node.to_string()),
-            None => (),
+        if let Some(node) = &self.right {
+            expression_str.push_str(&node.to_string());
         }
 
         expression_str
     }
 
+    ///
+    /// Evaluate the expression with `variable` bound to `substitute`.
+    /// Any other variable in the expression fails with [`EvalErrorKind::NoSubstitute`].
+    ///
     pub fn evaluate(&self, variable: &str, substitute: f64) -> Result<f64, EvalError> {
-        let left_val = &self.left.evaluate(variable, substitute)?;
+        self.evaluate_with(&(variable, substitute))
+    }
+
+    ///
+    /// Evaluate the expression, looking variables up in `ctx`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use expression_engine::{parse, EvalErrorKind, Environment};
+    ///
+    /// let exp = parse("x^2 + y").unwrap();
+    /// let mut env = Environment::new();
+    /// env.set("x", 3.0);
+    /// assert_eq!(
+    ///     exp.evaluate_with(&env).unwrap_err().kind,
+    ///     EvalErrorKind::NoSubstitute(String::from("y"))
+    /// );
+    ///
+    /// env.set("y", 1.0);
+    /// assert_eq!(exp.evaluate_with(&env).unwrap(), 10.0);
+    /// ```
+    ///
+    pub fn evaluate_with(&self, ctx: &(impl Context + ?Sized)) -> Result<f64, EvalError> {
+        let result = self.evaluate_node(ctx);
+        trace::evaluated(self, &result);
+        result
+    }
+
+    fn evaluate_node(&self, ctx: &(impl Context + ?Sized)) -> Result<f64, EvalError> {
+        let left_val = self.left.evaluate_with(ctx)?;
         match &self.right {
-            Some(right_node) => match &self.operator {
-                Some(operator) => {
-                    let right_val = right_node.evaluate(variable, substitute)?;
-                    Ok(match operator {
-                        Operator::Substract => left_val - right_val,
-                        Operator::Add => left_val + right_val,
-                        Operator::Divide => left_val / right_val,
-                        Operator::Multiply => left_val * right_val,
-                        Operator::Modulus => left_val % right_val,
-                        Operator::Power => left_val.powf(right_val),
-                    })
-                }
-                None => Ok(left_val * right_node.evaluate(variable, substitute)?), //default no operator means multiplication
-            },
-            None => Ok(*left_val),
+            Some(right_node) => {
+                //default no operator means multiplication
+                let operator = self.operator.unwrap_or(Operator::Multiply);
+                let value = apply_operator(
+                    ctx,
+                    operator,
+                    left_val,
+                    right_node.evaluate_with(ctx)?,
+                    self,
+                )?;
+                check_finite(ctx, value, self)
+            }
+            None => Ok(left_val),
         }
     }
 }
@@ -107,6 +321,34 @@ impl fmt::Display for ExpressionNode {
 }
 
 impl ExpressionNode {
+    /// Walk the expression with `visitor`, as the [`Expr`] tree it converts to.
+    pub fn visit(&self, visitor: &mut (impl Visitor + ?Sized)) {
+        Expr::from(self).visit(visitor)
+    }
+
+    /// Transform the expression with `folder`, as the [`Expr`] tree it converts to.
+    pub fn fold(&self, folder: &mut (impl Folder + ?Sized)) -> ExpressionNode {
+        Expr::from(self).fold(folder).into()
+    }
+
+    /// A node holding a single operand, without operator or right operand.
+    pub fn is_leaf(&self) -> bool {
+        self.operator.is_none() && self.right.is_none()
+    }
+
+    ///
+    /// Turn the node into an operand of an enclosing node, unwrapping plain leaves
+    /// and boxing everything else as a sub expression.
+    ///
+    pub fn into_operand(self) -> ExpressionNodeType {
+        match (self.operator, self.right) {
+            (None, None) => self.left,
+            (operator, right) => ExpressionNodeType::NodeOperand(Box::new(ExpressionNode::new(
+                self.left, operator, right,
+            ))),
+        }
+    }
+
     pub fn new(
         left: ExpressionNodeType,
         operator: Option<Operator>,
@@ -121,9 +363,14 @@ impl ExpressionNode {
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
+    use crate::context::Environment;
+    use crate::function::Arity;
     use crate::parser::parse;
+    use crate::trigonometric::AngleMode;
+    use std::collections::HashMap;
 
     #[test]
     fn evaluate_01() {
@@ -134,7 +381,7 @@ mod tests {
         assert_eq!(res.unwrap(), 0.5);
     }
 
-    // #[test] TODO fix
+    #[test]
     fn evaluate_02() {
         let exp = parse("3x");
         assert_eq!(exp.is_ok(), true);
@@ -143,6 +390,44 @@ mod tests {
         assert_eq!(res.unwrap(), 0.75);
     }
 
+    #[test]
+    fn evaluate_with_context() {
+        let exp = parse("x*y+z").unwrap();
+        let variables = HashMap::from([("x", 2.0), ("y", 3.0), ("z", 0.5)]);
+        let res = exp.evaluate_with(&variables);
+        assert_eq!(res.unwrap(), 6.5);
+
+        let res = exp.evaluate_with(&HashMap::from([("x", 2.0), ("y", 3.0)]));
+        assert_eq!(
+            res.unwrap_err().kind,
+            EvalErrorKind::NoSubstitute(String::from("z"))
+        );
+    }
+
+    #[test]
+    fn evaluate_identifiers() {
+        let exp = parse("rate*time + x_1 - x2").unwrap();
+        let variables = HashMap::from([("rate", 2.0), ("time", 3.0), ("x_1", 1.0), ("x2", 4.0)]);
+        assert_eq!(exp.evaluate_with(&variables).unwrap(), 3.0);
+        assert_eq!(exp.generate_expression(), "((rate*time)+x_1)-x2");
+
+        // names starting with a function name are not calls
+        let exp = parse("cost + tank * expected").unwrap();
+        let variables =
+            HashMap::from([("cost", 2.0), ("t", 3.0), ("tank", 4.0), ("expected", 0.5)]);
+        assert_eq!(exp.evaluate_with(&variables).unwrap(), 4.0);
+    }
+
+    #[test]
+    fn evaluate_unbound_variable() {
+        let exp = parse("x+y").unwrap();
+        let res = exp.evaluate("x", 1.0);
+        assert_eq!(
+            res.unwrap_err().kind,
+            EvalErrorKind::NoSubstitute(String::from("y"))
+        );
+    }
+
     #[test]
     fn evaluate_1() {
         let exp = parse("x+1");
@@ -197,6 +482,7 @@ mod tests {
         assert_eq!(res.unwrap(), 3.75);
     }
 
+    #[allow(clippy::approx_constant, clippy::excessive_precision)]
     const PI: f64 = 3.1415926535897932;
     const EPSILON: f64 = 0.0000000000000001;
     const TEST_MAX: f64 = 1.0e16;
@@ -207,7 +493,7 @@ mod tests {
             ExpressionNodeType::TrigonometricOperand((
                 TrigonometricFunction::Sin,
                 Box::new(ExpressionNode::new(
-                    ExpressionNodeType::VariableOperand('x'),
+                    ExpressionNodeType::VariableOperand(String::from("x")),
                     None,
                     None,
                 )),
@@ -234,7 +520,7 @@ mod tests {
             ExpressionNodeType::TrigonometricOperand((
                 TrigonometricFunction::Cos,
                 Box::new(ExpressionNode::new(
-                    ExpressionNodeType::VariableOperand('x'),
+                    ExpressionNodeType::VariableOperand(String::from("x")),
                     None,
                     None,
                 )),
@@ -261,7 +547,7 @@ mod tests {
             ExpressionNodeType::TrigonometricOperand((
                 TrigonometricFunction::Tan,
                 Box::new(ExpressionNode::new(
-                    ExpressionNodeType::VariableOperand('x'),
+                    ExpressionNodeType::VariableOperand(String::from("x")),
                     None,
                     None,
                 )),
@@ -286,7 +572,7 @@ mod tests {
     fn generate_expression_1() {
         let exp = ExpressionNode::new(
             ExpressionNodeType::NodeOperand(Box::new(ExpressionNode::new(
-                ExpressionNodeType::VariableOperand('x'),
+                ExpressionNodeType::VariableOperand(String::from("x")),
                 Some(Operator::Divide),
                 Some(ExpressionNodeType::ValueOperand(100.0)),
             ))),
@@ -301,7 +587,7 @@ mod tests {
     fn generate_expression_2() {
         let exp = ExpressionNode::new(
             ExpressionNodeType::NodeOperand(Box::new(ExpressionNode::new(
-                ExpressionNodeType::VariableOperand('x'),
+                ExpressionNodeType::VariableOperand(String::from("x")),
                 Some(Operator::Power),
                 Some(ExpressionNodeType::ValueOperand(2.0)),
             ))),
@@ -316,14 +602,14 @@ mod tests {
     fn generate_expression_3() {
         let exp = ExpressionNode::new(
             ExpressionNodeType::NodeOperand(Box::new(ExpressionNode::new(
-                ExpressionNodeType::VariableOperand('x'),
+                ExpressionNodeType::VariableOperand(String::from("x")),
                 Some(Operator::Power),
                 Some(ExpressionNodeType::ValueOperand(2.0)),
             ))),
             Some(Operator::Add),
             Some(ExpressionNodeType::NodeOperand(Box::new(
                 ExpressionNode::new(
-                    ExpressionNodeType::VariableOperand('x'),
+                    ExpressionNodeType::VariableOperand(String::from("x")),
                     Some(Operator::Power),
                     Some(ExpressionNodeType::ValueOperand(3.0)),
                 ),
@@ -333,13 +619,150 @@ mod tests {
         assert_eq!(format!("{}", exp), "(x^2)+(x^3)");
     }
 
+    #[test]
+    fn evaluate_negate() {
+        let exp = parse("-x^2");
+        assert!(exp.is_ok());
+        let res = exp.unwrap().evaluate("x", 3.0);
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), -9.0);
+    }
+
+    #[test]
+    fn generate_expression_negate() {
+        let exp = ExpressionNode::new(
+            ExpressionNodeType::NegateOperand(Box::new(ExpressionNode::new(
+                ExpressionNodeType::VariableOperand(String::from("x")),
+                Some(Operator::Add),
+                Some(ExpressionNodeType::ValueOperand(1.0)),
+            ))),
+            Some(Operator::Multiply),
+            Some(ExpressionNodeType::NegateOperand(Box::new(
+                ExpressionNode::new(ExpressionNodeType::ValueOperand(3.0), None, None),
+            ))),
+        );
+        assert_eq!(exp.generate_expression(), "-(x+1)*-3");
+        assert_eq!(format!("{}", exp), "-(x+1)*-3");
+    }
+
+    #[test]
+    fn evaluate_function_call() {
+        let exp = ExpressionNode::new(
+            ExpressionNodeType::FunctionCall {
+                name: String::from("sigmoid"),
+                args: vec![ExpressionNode::new(
+                    ExpressionNodeType::VariableOperand(String::from("x")),
+                    None,
+                    None,
+                )],
+            },
+            None,
+            None,
+        );
+        assert_eq!(exp.generate_expression(), "sigmoid(x)");
+        assert_eq!(
+            exp.evaluate("x", 0.0).unwrap_err().kind,
+            EvalErrorKind::UnknownFunction(String::from("sigmoid"))
+        );
+
+        let mut env = Environment::new();
+        env.register_function("sigmoid", Arity::Exact(1), |args| {
+            Ok(1.0 / (1.0 + (-args[0]).exp()))
+        });
+        env.set("x", 0.0);
+        assert_eq!(exp.evaluate_with(&env).unwrap(), 0.5);
+
+        env.register_function("sigmoid", Arity::Exact(2), |args| Ok(args[0]));
+        assert_eq!(
+            exp.evaluate_with(&env).unwrap_err().kind,
+            EvalErrorKind::InvalidExpression
+        );
+    }
+
+    #[test]
+    fn evaluate_strict() {
+        let mut env = Environment::new();
+        env.set("x", 0.0);
+        let kind = |source: &str, env: &Environment| {
+            parse(source).unwrap().evaluate_with(env).unwrap_err().kind
+        };
+        let division_by_zero = |expression: &str| EvalErrorKind::DivisionByZero {
+            expression: expression.to_string(),
+        };
+
+        assert_eq!(
+            parse("1/x").unwrap().evaluate_with(&env).unwrap(),
+            f64::INFINITY
+        );
+        assert!(parse("x%x").unwrap().evaluate_with(&env).unwrap().is_nan());
+        assert!(parse("asin(x + 2)")
+            .unwrap()
+            .evaluate_with(&env)
+            .unwrap()
+            .is_nan());
+        assert_eq!(
+            kind("sqrt(x - 1)", &env),
+            EvalErrorKind::DomainError {
+                function: String::from("sqrt"),
+                value: -1.0
+            }
+        );
+
+        env.set_eval_mode(EvalMode::Strict);
+        assert_eq!(kind("1/0", &env), division_by_zero("1/0"));
+        assert_eq!(kind("1 + 0^-1", &env), division_by_zero("0^-1"));
+        assert_eq!(kind("2(3 % x)", &env), division_by_zero("3%x"));
+        assert_eq!(
+            kind("tan(pi/2)", &env),
+            EvalErrorKind::DomainError {
+                function: String::from("tan"),
+                value: PI / 2.0
+            }
+        );
+        assert_eq!(
+            kind("acos(x + 2)", &env),
+            EvalErrorKind::DomainError {
+                function: String::from("acos"),
+                value: 2.0
+            }
+        );
+        assert_eq!(
+            kind("(x-8)^(1/3)", &env),
+            EvalErrorKind::NonFinite {
+                expression: String::from("(x-8)^(1/3)")
+            }
+        );
+        assert_eq!(
+            kind("x + inf", &env),
+            EvalErrorKind::NonFinite {
+                expression: String::from("inf")
+            }
+        );
+        assert_eq!(
+            parse("sin(x) + 1/(x+2)")
+                .unwrap()
+                .evaluate_with(&env)
+                .unwrap(),
+            0.5
+        );
+
+        env.set_angle_mode(AngleMode::Degrees);
+        assert_eq!(
+            kind("tan(90)", &env),
+            EvalErrorKind::DomainError {
+                function: String::from("tan"),
+                value: 90.0
+            }
+        );
+    }
+
     #[test]
     fn generate_expression_sin() {
         let exp = ExpressionNode::new(
             ExpressionNodeType::TrigonometricOperand((
                 TrigonometricFunction::Sin,
                 Box::new(ExpressionNode::new(
-                    ExpressionNodeType::VariableOperand('x'),
+                    ExpressionNodeType::VariableOperand(String::from("x")),
                     None,
                     None,
                 )),
@@ -357,7 +780,7 @@ mod tests {
             ExpressionNodeType::TrigonometricOperand((
                 TrigonometricFunction::Cos,
                 Box::new(ExpressionNode::new(
-                    ExpressionNodeType::VariableOperand('x'),
+                    ExpressionNodeType::VariableOperand(String::from("x")),
                     None,
                     None,
                 )),
@@ -375,7 +798,7 @@ mod tests {
             ExpressionNodeType::TrigonometricOperand((
                 TrigonometricFunction::Tan,
                 Box::new(ExpressionNode::new(
-                    ExpressionNodeType::VariableOperand('x'),
+                    ExpressionNodeType::VariableOperand(String::from("x")),
                     Some(Operator::Power),
                     Some(ExpressionNodeType::ValueOperand(3.0)),
                 )),
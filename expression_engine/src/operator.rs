@@ -10,7 +10,17 @@ pub enum Operator {
     Power,
 }
 
+/// Direction in which a chain of equal-precedence operators groups.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Associativity {
+    Left,
+    Right,
+}
+
 impl Operator {
+    /// Binding power of the prefix `-` and `+` operators.
+    pub const PREFIX_PRECEDENCE: u8 = 3;
+
     pub fn from(op: &str) -> Option<Operator> {
         match op {
             "+" => Some(Operator::Add),
@@ -23,6 +33,45 @@ impl Operator {
         }
     }
 
+    ///
+    /// Binding power used by the parser, higher binds tighter.
+    ///
+    /// | Operator      | Precedence | Associativity |
+    /// |---------------|------------|---------------|
+    /// | `+` `-`       | 1          | left          |
+    /// | `*` `/` `%`   | 2          | left          |
+    /// | `^`           | 4          | right         |
+    ///
+    /// Prefix `-` and `+` bind at [`Operator::PREFIX_PRECEDENCE`], between `*` and `^`,
+    /// so that `-x^2` reads as `-(x^2)` and `2^-x` as `2^(-x)`.
+    ///
+    pub fn precedence(&self) -> u8 {
+        match self {
+            Operator::Add | Operator::Substract => 1,
+            Operator::Multiply | Operator::Divide | Operator::Modulus => 2,
+            Operator::Power => 4,
+        }
+    }
+
+    pub fn associativity(&self) -> Associativity {
+        match self {
+            Operator::Power => Associativity::Right,
+            _ => Associativity::Left,
+        }
+    }
+
+    /// Apply the operator to its evaluated operands.
+    pub fn apply(&self, left: f64, right: f64) -> f64 {
+        match self {
+            Operator::Substract => left - right,
+            Operator::Add => left + right,
+            Operator::Divide => left / right,
+            Operator::Multiply => left * right,
+            Operator::Modulus => left % right,
+            Operator::Power => left.powf(right),
+        }
+    }
+
     pub fn value_in_operator(&self) -> char {
         match self {
             Operator::Add => '+',
@@ -42,6 +91,7 @@ impl fmt::Display for Operator {
     }
 }
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
 
@@ -59,6 +109,24 @@ mod tests {
         assert_eq!(Operator::Modulus > Operator::Power, false);
     }
 
+    #[test]
+    fn precedence_in_operator() {
+        assert_eq!(Operator::Add.precedence(), Operator::Substract.precedence());
+        assert_eq!(
+            Operator::Multiply.precedence(),
+            Operator::Divide.precedence()
+        );
+        assert_eq!(
+            Operator::Multiply.precedence(),
+            Operator::Modulus.precedence()
+        );
+        assert!(Operator::Multiply.precedence() > Operator::Add.precedence());
+        assert!(Operator::Power.precedence() > Operator::Multiply.precedence());
+        assert_eq!(Operator::Substract.associativity(), Associativity::Left);
+        assert_eq!(Operator::Divide.associativity(), Associativity::Left);
+        assert_eq!(Operator::Power.associativity(), Associativity::Right);
+    }
+
     #[test]
     fn values_in_operatoe() {
         assert_eq!(Operator::Add.value_in_operator(), '+');
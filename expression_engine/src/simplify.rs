@@ -0,0 +1,520 @@
+//!
+//! Algebraic simplification: folding of numeric subtrees, identities such as `x*1` and `x+0`,
+//! and collection of like terms in sums and of like factors in products.
+//!
+
+use crate::elementary::ElementaryFunction;
+use crate::expr::{Expr, UnaryOperator};
+use crate::node::ExpressionNode;
+use crate::operator::Operator;
+use crate::visit::Folder;
+
+/// A product as a numeric coefficient and `(base, exponent)` factors in order of appearance.
+struct Product {
+    coefficient: f64,
+    factors: Vec<(Expr, Expr)>,
+}
+
+impl Product {
+    fn of(expr: &Expr) -> Product {
+        let mut product = Product {
+            coefficient: 1.0,
+            factors: Vec::new(),
+        };
+        product.collect(expr, false);
+        product
+            .factors
+            .retain(|(_, exponent)| value_of(exponent) != Some(0.0));
+        product
+    }
+
+    /// Multiply by `expr`, or divide by it if `divide`, combining factors of the same base.
+    fn collect(&mut self, expr: &Expr, divide: bool) {
+        match expr {
+            Expr::Binary {
+                op: Operator::Multiply,
+                left,
+                right,
+            } => {
+                self.collect(left, divide);
+                self.collect(right, divide);
+            }
+            Expr::Binary {
+                op: Operator::Divide,
+                left,
+                right,
+            } => {
+                self.collect(left, divide);
+                self.collect(right, !divide);
+            }
+            Expr::Unary {
+                op: UnaryOperator::Negate,
+                operand,
+            } => {
+                self.coefficient = -self.coefficient;
+                self.collect(operand, divide);
+            }
+            // a zero divisor stays a factor so the division by zero is kept
+            Expr::Number { value, .. } if !(divide && *value == 0.0) => {
+                if divide {
+                    self.coefficient /= value;
+                } else {
+                    self.coefficient *= value;
+                }
+            }
+            Expr::Binary {
+                op: Operator::Power,
+                left,
+                right,
+            } => self.push(left, right.as_ref().clone(), divide),
+            _ => self.push(expr, number(1.0), divide),
+        }
+    }
+
+    fn push(&mut self, base: &Expr, exponent: Expr, divide: bool) {
+        let exponent = if divide { negate(exponent) } else { exponent };
+        match self.factors.iter_mut().find(|(other, _)| other == base) {
+            Some((_, other)) => {
+                *other = simplify_sum(&binary(Operator::Add, other.clone(), exponent));
+            }
+            None => self.factors.push((base.clone(), exponent)),
+        }
+    }
+
+    /// Whether a factor divides by zero, a zero base with an exponent not known to be positive.
+    fn divides_by_zero(&self) -> bool {
+        self.factors.iter().any(|(base, exponent)| {
+            value_of(base) == Some(0.0) && !matches!(value_of(exponent), Some(value) if value > 0.0)
+        })
+    }
+
+    /// The product with `coefficient` in place of its own.
+    fn to_expr(&self, coefficient: f64) -> Expr {
+        if coefficient == 0.0 && !self.divides_by_zero() {
+            return number(0.0);
+        }
+        let mut numerator = Vec::new();
+        let mut denominator = Vec::new();
+        for (base, exponent) in &self.factors {
+            match exponent {
+                Expr::Number { value, .. } if *value < 0.0 => {
+                    denominator.push(power(base.clone(), number(-value)))
+                }
+                _ => numerator.push(power(base.clone(), exponent.clone())),
+            }
+        }
+        if coefficient == -1.0 && !numerator.is_empty() {
+            // `-a*b` rather than `-(a*b)`, both read as the negation of the product
+            numerator[0] = negate(numerator[0].clone());
+        } else if coefficient != 1.0 || numerator.is_empty() {
+            numerator.insert(0, number(coefficient));
+        }
+        let numerator = multiply_all(numerator);
+        if denominator.is_empty() {
+            numerator
+        } else {
+            binary(Operator::Divide, numerator, multiply_all(denominator))
+        }
+    }
+}
+
+/// Simplifies each node once its children are simplified.
+struct Simplifier;
+
+impl Folder for Simplifier {
+    fn fold_number(&mut self, value: f64, literal: Option<String>) -> Expr {
+        match literal {
+            Some(literal) => Expr::Number {
+                value,
+                literal: Some(literal),
+            },
+            None => number(value),
+        }
+    }
+
+    fn fold_unary(&mut self, op: UnaryOperator, operand: Expr) -> Expr {
+        let operand = self.fold_expr(operand);
+        match op {
+            UnaryOperator::Negate => simplify_sum(&negate(operand)),
+            // the value of a `°` angle depends on the angle mode, so it is never folded
+            UnaryOperator::Degrees => Expr::Unary {
+                op,
+                operand: Box::new(operand),
+            },
+        }
+    }
+
+    fn fold_binary(&mut self, op: Operator, left: Expr, right: Expr) -> Expr {
+        let (left, right) = (self.fold_expr(left), self.fold_expr(right));
+        match op {
+            Operator::Add | Operator::Substract | Operator::Multiply | Operator::Divide => {
+                simplify_sum(&binary(op, left, right))
+            }
+            Operator::Power => match (value_of(&left), value_of(&right)) {
+                (_, Some(0.0)) => number(1.0),
+                (_, Some(1.0)) => left,
+                (Some(1.0), _) => number(1.0),
+                (Some(base), Some(exponent)) => fold(base.powf(exponent), op, left, right),
+                _ => binary(op, left, right),
+            },
+            Operator::Modulus => match (value_of(&left), value_of(&right)) {
+                (Some(dividend), Some(divisor)) => fold(dividend % divisor, op, left, right),
+                _ => binary(op, left, right),
+            },
+        }
+    }
+
+    fn fold_call(&mut self, name: String, args: Vec<Expr>) -> Expr {
+        let args: Vec<Expr> = args.into_iter().map(|arg| self.fold_expr(arg)).collect();
+        // trigonometric functions depend on the angle mode and user functions on the context,
+        // so only the elementary functions are evaluated
+        let values: Option<Vec<f64>> = args.iter().map(value_of).collect();
+        let value = ElementaryFunction::from(&name)
+            .filter(|function| function.arity().accepts(args.len()))
+            .zip(values)
+            .and_then(|(function, values)| function.evaluate(&values).ok())
+            .filter(|value| value.is_finite());
+        match value {
+            Some(value) => number(value),
+            None => Expr::Call { name, args },
+        }
+    }
+}
+
+/// `expr`, a sum, difference, product, quotient or negation of simplified operands, with
+/// numbers folded and like terms collected.
+fn simplify_sum(expr: &Expr) -> Expr {
+    let mut terms: Vec<(f64, Product)> = Vec::new();
+    let mut constant = 0.0;
+    collect_terms(expr, 1.0, &mut |sign, term| {
+        let product = Product::of(term);
+        let coefficient = sign * product.coefficient;
+        if product.factors.is_empty() {
+            constant += coefficient;
+        } else {
+            match terms
+                .iter_mut()
+                .find(|(_, other)| other.factors == product.factors)
+            {
+                Some((other, _)) => *other += coefficient,
+                None => terms.push((coefficient, product)),
+            }
+        }
+    });
+    // like `fold`, keep the operations whose folded value would not be finite
+    if !constant.is_finite()
+        || terms
+            .iter()
+            .any(|(coefficient, _)| !coefficient.is_finite())
+    {
+        return expr.clone();
+    }
+
+    let mut sum: Option<Expr> = None;
+    let terms = terms
+        .iter()
+        .filter(|(coefficient, product)| *coefficient != 0.0 || product.divides_by_zero())
+        .map(|(coefficient, product)| (*coefficient, Some(product)))
+        .chain((constant != 0.0).then_some((constant, None)));
+    for (coefficient, product) in terms {
+        let term = |coefficient: f64| match product {
+            Some(product) => product.to_expr(coefficient),
+            None => number(coefficient),
+        };
+        sum = Some(match sum {
+            None => term(coefficient),
+            Some(sum) if coefficient < 0.0 => binary(Operator::Substract, sum, term(-coefficient)),
+            Some(sum) => binary(Operator::Add, sum, term(coefficient)),
+        });
+    }
+    sum.unwrap_or_else(|| number(0.0))
+}
+
+/// Call `term` with each term of the sum `expr` and its sign.
+fn collect_terms(expr: &Expr, sign: f64, term: &mut impl FnMut(f64, &Expr)) {
+    match expr {
+        Expr::Binary {
+            op: Operator::Add,
+            left,
+            right,
+        } => {
+            collect_terms(left, sign, term);
+            collect_terms(right, sign, term);
+        }
+        Expr::Binary {
+            op: Operator::Substract,
+            left,
+            right,
+        } => {
+            collect_terms(left, sign, term);
+            collect_terms(right, -sign, term);
+        }
+        Expr::Unary {
+            op: UnaryOperator::Negate,
+            operand,
+        } => collect_terms(operand, -sign, term),
+        _ => term(sign, expr),
+    }
+}
+
+/// `value` as a number if it is finite, otherwise the operation it came from.
+fn fold(value: f64, op: Operator, left: Expr, right: Expr) -> Expr {
+    if value.is_finite() {
+        number(value)
+    } else {
+        binary(op, left, right)
+    }
+}
+
+fn value_of(expr: &Expr) -> Option<f64> {
+    match expr {
+        Expr::Number { value, .. } => Some(*value),
+        _ => None,
+    }
+}
+
+/// `value` as a number, with `-0` as `0` so it does not print a sign.
+pub(crate) fn number(value: f64) -> Expr {
+    Expr::Number {
+        value: if value == 0.0 { 0.0 } else { value },
+        literal: None,
+    }
+}
+
+pub(crate) fn negate(expr: Expr) -> Expr {
+    match expr {
+        Expr::Number { value, .. } => number(-value),
+        expr => Expr::Unary {
+            op: UnaryOperator::Negate,
+            operand: Box::new(expr),
+        },
+    }
+}
+
+pub(crate) fn binary(op: Operator, left: Expr, right: Expr) -> Expr {
+    Expr::Binary {
+        op,
+        left: Box::new(left),
+        right: Box::new(right),
+    }
+}
+
+fn power(base: Expr, exponent: Expr) -> Expr {
+    match value_of(&exponent) {
+        Some(1.0) => base,
+        _ => binary(Operator::Power, base, exponent),
+    }
+}
+
+fn multiply_all(factors: Vec<Expr>) -> Expr {
+    factors
+        .into_iter()
+        .reduce(|product, factor| binary(Operator::Multiply, product, factor))
+        .unwrap_or_else(|| number(1.0))
+}
+
+impl Expr {
+    ///
+    /// Simplify the expression: fold numeric subtrees, apply identities such as `x*1`,
+    /// `x+0`, `x^1` and `0*x`, collect like terms such as `x + 2x` into `3*x` and like
+    /// factors such as `x*x` into `x^2`, cancelling `x-x` and `x/x`.
+    ///
+    /// Wherever the expression evaluates to a finite number, the simplified one evaluates
+    /// to the same up to rounding. It may be defined where the expression is not, `x/x`
+    /// simplifies to `1` although it is undefined at `0`. Trigonometric functions, `°`
+    /// angles and functions other than the elementary ones are not evaluated, as they depend
+    /// on the context.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use expression_engine::parse_expr;
+    ///
+    /// let simplify = |source: &str| parse_expr(source).unwrap().simplify().to_string();
+    ///
+    /// assert_eq!(simplify("2*3+x*1+0"), "x+6");
+    /// assert_eq!(simplify("x*x + 2x - x^2"), "2*x");
+    /// assert_eq!(simplify("sqrt(16)*y/y + sin(0)"), "sin(0)+4");
+    /// ```
+    ///
+    pub fn simplify(&self) -> Expr {
+        self.clone().fold(&mut Simplifier)
+    }
+}
+
+impl ExpressionNode {
+    ///
+    /// Simplify the expression, see [`Expr::simplify`]. Parentheses of the original are not
+    /// kept, nested operands only wrap the subexpressions that need them.
+    ///
+    pub fn simplify(&self) -> ExpressionNode {
+        Expr::from(self).simplify().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::Environment;
+    use crate::expr::Expr;
+    use crate::node::{EvalErrorKind, EvalMode};
+    use crate::parser::{parse, parse_expr};
+
+    fn simplify(source: &str) -> String {
+        parse_expr(source).unwrap().simplify().to_string()
+    }
+
+    #[test]
+    fn simplify_fold_constants() {
+        assert_eq!(simplify("2*3+4"), "10");
+        assert_eq!(simplify("2^10 - 24"), "1000");
+        assert_eq!(simplify("-(3 - 5)"), "2");
+        assert_eq!(simplify("-0 * x"), "0");
+        let negative_zero = Expr::Number {
+            value: -0.0,
+            literal: None,
+        };
+        assert_eq!(negative_zero.simplify().to_string(), "0");
+        assert_eq!(simplify("7 % 4 * x"), "3*x");
+        assert_eq!(simplify("ln(1) + abs(-2) * x"), "2*x");
+        assert_eq!(simplify("0x10 / 4"), "4");
+        assert_eq!(simplify("2pi"), "2*pi");
+        assert_eq!(simplify("sin(30°) + cos(0)"), "sin(30°)+cos(0)");
+        assert_eq!(simplify("1/0 + ln(0)"), "1/0+ln(0)");
+        assert_eq!(simplify("0^-1"), "0^(-1)");
+    }
+
+    #[test]
+    fn simplify_keeps_overflow() {
+        assert_eq!(simplify("1e308*10"), "1e308*10");
+        assert_eq!(simplify("1e308*10 - 1e308*10"), "1e308*10-1e308*10");
+        assert_eq!(simplify("1e308*10*x + x"), "1e308*10*x+x");
+        assert_eq!(simplify("1e308*10/10 + 1"), "1e308*10/10+1");
+
+        let once = parse_expr("1e308*10 - 1e308*10").unwrap().simplify();
+        let reparsed = parse_expr(&once.to_string()).unwrap();
+        assert_eq!(reparsed.simplify(), once);
+        assert!(reparsed.evaluate("x", 0.0).unwrap().is_nan());
+    }
+
+    #[test]
+    fn simplify_keeps_zero_divisor() {
+        assert_eq!(simplify("0/0"), "0/0");
+        assert_eq!(simplify("0*x/0"), "0/0");
+        assert_eq!(simplify("x*0/0"), "0/0");
+        assert_eq!(simplify("x/0*0"), "0*x/0");
+        assert_eq!(simplify("0*0^-1 + x"), "0/0+x");
+        assert_eq!(simplify("1/0"), "1/0");
+
+        let mut env = Environment::new();
+        env.set("x", 2.0);
+        env.set_eval_mode(EvalMode::Strict);
+        for source in ["0/0", "0*x/0", "x*0/0", "x/0*0"] {
+            let simplified = parse(source).unwrap().simplify();
+            assert!(
+                matches!(
+                    simplified.evaluate_with(&env).unwrap_err().kind,
+                    EvalErrorKind::DivisionByZero { .. }
+                ),
+                "{} simplifies to {}",
+                source,
+                simplified
+            );
+        }
+    }
+
+    #[test]
+    fn simplify_identities() {
+        assert_eq!(simplify("x*1"), "x");
+        assert_eq!(simplify("1*x + 0"), "x");
+        assert_eq!(simplify("x^1 - 0"), "x");
+        assert_eq!(simplify("x^0"), "1");
+        assert_eq!(simplify("0*sin(x)"), "0");
+        assert_eq!(simplify("x - x"), "0");
+        assert_eq!(simplify("x / x"), "1");
+        assert_eq!(simplify("x / x + 1"), "2");
+        assert_eq!(simplify("x / 1"), "x");
+        assert_eq!(simplify("1^y"), "1");
+        assert_eq!(simplify("--x"), "x");
+        assert_eq!(simplify("0 - x"), "-x");
+        assert_eq!(simplify("y - -x"), "y+x");
+    }
+
+    #[test]
+    fn simplify_like_terms() {
+        assert_eq!(simplify("x + 2x + 3"), "3*x+3");
+        assert_eq!(simplify("x*y + 2*x*y - y"), "3*x*y-y");
+        assert_eq!(simplify("sin(x) + sin(x)"), "2*sin(x)");
+        assert_eq!(simplify("x*x*x"), "x^3");
+        assert_eq!(simplify("x^2 * x / x^3"), "1");
+        assert_eq!(simplify("2x * 3y / (4x)"), "1.5*y");
+        assert_eq!(simplify("x^a * x^b"), "x^(a+b)");
+        assert_eq!(simplify("-x*2 + 1"), "-2*x+1");
+        assert_eq!(simplify("1 - x"), "-x+1");
+        assert_eq!(simplify("x/y/z"), "x/(y*z)");
+        assert_eq!(simplify("(x+1)(x+1)"), "(x+1)^2");
+    }
+
+    #[test]
+    fn simplify_is_stable() {
+        for source in [
+            "x^3 - 2x^2 + x/4 - 1",
+            "3x*y/(2z) - x",
+            "-(x+1)^2 + atan2(y, 2x)",
+        ] {
+            let once = parse_expr(source).unwrap().simplify();
+            assert_eq!(once.simplify(), once, "{}", source);
+        }
+    }
+
+    #[test]
+    fn simplify_keeps_value() {
+        let mut env = Environment::new();
+        env.set("x", 1.5);
+        env.set("y", -0.25);
+        for source in [
+            "2*3+x*1+0",
+            "x^3 - 2x^2 + x/4 - 1 + x^2",
+            "3x*y/(2x) - y + x % 2",
+            "-(x+1)^2 * 2 + atan2(y, 2x)",
+            "sqrt(x)*sqrt(x) - x + exp(y)",
+        ] {
+            let node = parse(source).unwrap();
+            let simplified = node.simplify();
+            let (original, simplified) = (
+                node.evaluate_with(&env).unwrap(),
+                simplified.evaluate_with(&env).unwrap(),
+            );
+            assert!((original - simplified).abs() < 1e-12, "{}", source);
+        }
+    }
+
+    #[test]
+    fn simplify_legacy_node() {
+        let node = parse("2*3+x*1+0").unwrap().simplify();
+        assert_eq!(node.to_string(), "x+6");
+        assert_eq!(node.evaluate("x", 1.0).unwrap(), 7.0);
+
+        // nested parentheses flatten to the operands they wrap
+        let node = parse("((((x))))*((2))").unwrap().simplify();
+        assert_eq!(node.to_string(), "2*x");
+        assert_eq!(parse("((y))").unwrap().simplify().to_string(), "y");
+
+        // the printed tree reads back as the same formula
+        for (source, x) in [("(0-2)^x", 2.0), ("x*(3-5)", 1.5), ("(1-4)^2 - x", 0.5)] {
+            let node = parse(source).unwrap().simplify();
+            let reparsed = parse(&node.to_string()).unwrap();
+            assert_eq!(
+                reparsed.evaluate("x", x).unwrap(),
+                node.evaluate("x", x).unwrap(),
+                "{} simplifies to {}",
+                source,
+                node
+            );
+            assert_eq!(
+                node.evaluate("x", x).unwrap(),
+                parse(source).unwrap().evaluate("x", x).unwrap()
+            );
+        }
+        assert_eq!(parse("(0-2)^x").unwrap().simplify().to_string(), "(-2)^x");
+    }
+}
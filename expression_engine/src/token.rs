@@ -1,8 +1,53 @@
-use regex::Regex;
+use crate::context::Context;
+use crate::diagnostic::{edit_distance, suggest};
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::Range;
+
+/// Byte range `start..end` of a token or an error in the parsed expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    ///
+    /// Range of characters covered in `source`, the expression the span was taken from,
+    /// for placing markers under text that is not plain ASCII.
+    ///
+    pub fn char_range(&self, source: &str) -> Range<usize> {
+        let start = source[..self.start].chars().count();
+        start..start + source[self.start..self.end].chars().count()
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ParseError {
     pub kind: ErrorKind,
+    /// Location of the offending input, empty at the end of the expression when it ended early.
+    pub span: Span,
+    /// What was expected at `span` instead, when something specific was.
+    pub expected: Option<Expected>,
+}
+
+impl ParseError {
+    pub fn new(kind: ErrorKind, span: Span) -> Self {
+        Self {
+            kind,
+            span,
+            expected: None,
+        }
+    }
+
+    pub fn expecting(mut self, expected: Expected) -> Self {
+        self.expected = Some(expected);
+        self
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -11,200 +56,372 @@ pub enum ErrorKind {
     InvalidOpenCloseParantheses,
     InvalidNumberParsed,
     InvalidExpression,
+    /// A name is called like a function, `name(...)`, but no such function is known.
+    UnknownFunction(String),
+    /// A function is called with a number of arguments it does not accept.
+    InvalidArgumentCount,
+    /// A character that is not part of any token, such as `$` or `&`.
+    UnexpectedCharacter(char),
+    /// An opening bracket that is never closed, such as the `(` of `(x+1`.
+    UnclosedBracket(char),
+    /// A closing bracket without an opening one, such as the `)` of `x+1)`.
+    UnmatchedBracket(char),
+    /// A closing bracket of another kind than the bracket it closes, such as `(x+1]`.
+    MismatchedBracket {
+        open: char,
+        close: char,
+    },
+}
+
+/// Input the parser expected where it failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expected {
+    /// A number, variable, function call or parenthesized expression.
+    Operand,
+    OperatorOrEnd,
+    ClosingBracket(char),
+    /// `,` before another argument or the bracket closing the argument list.
+    CommaOrClosingBracket(char),
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expected::Operand => write!(f, "a number, variable, function or '('")?,
+            Expected::OperatorOrEnd => write!(f, "an operator or the end of the expression")?,
+            Expected::ClosingBracket(close) => write!(f, "'{}'", close)?,
+            Expected::CommaOrClosingBracket(close) => write!(f, "',' or '{}'", close)?,
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum Token<'a> {
     Number(f64),
     Variable(&'a str),
-    Trigonometric(&'a str),
+    Function(&'a str),
     Operator(&'a str),
+    /// A parenthesis or bracket, `(`, `)`, `[`, `]`, `{` or `}`.
     Parenthesis(&'a str),
+    Comma,
+    /// The `°` suffix of an angle in degrees.
+    Degree,
 }
 
-pub fn validate(tokens: &[Token<'_>]) -> Result<(), ParseError> {
-    let mut paranthesis_count = 0;
-    let mut open_close = false;
+/// A token together with its location in the expression.
+#[derive(Debug, Clone)]
+pub struct SpannedToken<'a> {
+    pub token: Token<'a>,
+    pub span: Span,
+}
+
+impl<'a> SpannedToken<'a> {
+    pub fn new(token: Token<'a>, span: Span) -> Self {
+        Self { token, span }
+    }
+}
+
+/// The closing bracket matching the opening bracket `open`, `)` for `(`, `]` for `[` and `}` for `{`.
+pub fn closing_bracket(open: &str) -> Option<&'static str> {
+    match open {
+        "(" => Some(")"),
+        "[" => Some("]"),
+        "{" => Some("}"),
+        _ => None,
+    }
+}
+
+/// The bracket of a [`Token::Parenthesis`] as a character.
+pub fn bracket_char(bracket: &str) -> char {
+    bracket.chars().next().unwrap_or_default()
+}
+
+///
+/// Check that every bracket is closed by a bracket of its own kind, innermost first,
+/// and that there is something besides brackets.
+///
+pub fn validate(tokens: &[SpannedToken<'_>]) -> Result<(), ParseError> {
+    let mut open_brackets: Vec<(&str, Span)> = Vec::new();
     let mut all_other_count = 0;
-    for token in tokens {
-        if let Token::Parenthesis(p_type) = token {
-            if *p_type == "(" && !open_close {
-                paranthesis_count += 1;
-                open_close = true;
+    for spanned in tokens {
+        let Token::Parenthesis(bracket) = spanned.token else {
+            all_other_count += 1;
+            continue;
+        };
+        if closing_bracket(bracket).is_some() {
+            open_brackets.push((bracket, spanned.span));
+            continue;
+        }
+        match open_brackets.pop() {
+            Some((open, _)) if closing_bracket(open) == Some(bracket) => (),
+            Some((open, _)) => {
+                let close = closing_bracket(open).map_or(')', bracket_char);
+                return Err(ParseError::new(
+                    ErrorKind::MismatchedBracket {
+                        open: bracket_char(open),
+                        close: bracket_char(bracket),
+                    },
+                    spanned.span,
+                )
+                .expecting(Expected::ClosingBracket(close)));
             }
-            if *p_type == ")" && open_close {
-                paranthesis_count -= 1;
-                open_close = false;
+            None => {
+                return Err(ParseError::new(
+                    ErrorKind::UnmatchedBracket(bracket_char(bracket)),
+                    spanned.span,
+                ))
             }
-        } else {
-            all_other_count += 1
         }
     }
 
-    if paranthesis_count != 0 {
-        return Err(ParseError {
-            kind: ErrorKind::InvalidOpenCloseParantheses,
-        });
+    if let Some((open, span)) = open_brackets.pop() {
+        let close = closing_bracket(open).map_or(')', bracket_char);
+        return Err(
+            ParseError::new(ErrorKind::UnclosedBracket(bracket_char(open)), span)
+                .expecting(Expected::ClosingBracket(close)),
+        );
     }
 
     if all_other_count == 0 {
-        return Err(ParseError {
-            kind: ErrorKind::Empty,
-        });
+        let span = match (tokens.first(), tokens.last()) {
+            (Some(first), Some(last)) => Span::new(first.span.start, last.span.end),
+            _ => Span::default(),
+        };
+        return Err(ParseError::new(ErrorKind::Empty, span));
     }
 
     Ok(())
 }
 
-pub fn trim_parantheses<'a>(tokens: &[Token<'a>]) -> Vec<Token<'a>> {
-    let mut current: &[Token<'a>] = tokens;
-
-    while let Some(last) = current.last() {
-        if let Token::Parenthesis(p_end) = last {
-            if let Some(Token::Parenthesis(p_start)) = current.first() {
-                println!("{}", p_start);
-                println!("{}", p_end);
-                let trimmed: &[Token<'_>] = &current[1..current.len() - 1];
-                if *p_start == "(" && *p_end == ")" && validate(trimmed).is_ok() {
-                    println!("Trimming parantheses");
-                    current = trimmed;
-                    continue;
-                }
-            }
-        }
-        break;
-    }
-    current.to_vec()
+/// Tokenize, recognising the functions and constants known to `ctx`.
+pub fn tokenize_with<'a>(
+    expression: &'a str,
+    ctx: &(impl Context + ?Sized),
+) -> Result<Vec<SpannedToken<'a>>, ParseError> {
+    Lexer::new(expression, ctx).collect()
 }
 
-pub fn tokenize(expression: &str) -> Result<Vec<Token>, ParseError> {
-    // Regex with named groups for categorization
-    let re = Regex::new(
-        r"(?P<number>\d+\.\d+|\d+)|(?P<trigonometric>sin|cosec|cos|tan|sec|cot)|(?P<variable>\w+)|(?P<operator>[+\-*/^%])|(?P<parenthesis>[()])",
-    )
-    .unwrap();
-
-    let mut tokens = Vec::new();
-
-    // Match each token and categorize it
-    for cap in re.captures_iter(expression) {
-        if let Some(number) = cap.name("number") {
-            tokens.push(Token::Number(number.as_str().parse().map_err(|_| {
-                ParseError {
-                    kind: ErrorKind::InvalidNumberParsed,
-                }
-            })?));
-        } else if let Some(operator) = cap.name("operator") {
-            tokens.push(Token::Operator(operator.as_str()));
-        } else if let Some(operator) = cap.name("variable") {
-            tokens.push(Token::Variable(operator.as_str()));
-        } else if let Some(trigonometric) = cap.name("trigonometric") {
-            tokens.push(Token::Trigonometric(trigonometric.as_str()));
-        } else if let Some(parenthesis) = cap.name("parenthesis") {
-            tokens.push(Token::Parenthesis(parenthesis.as_str()));
-        }
-    }
-
-    Ok(tokens)
+///
+/// Single pass lexer over an expression, yielding tokens that borrow from it.
+/// Iteration stops after the first error.
+///
+pub struct Lexer<'a, 'c, C: Context + ?Sized> {
+    expression: &'a str,
+    position: usize,
+    /// End of the identifier last split after a function name. Its rest is never a call.
+    split_end: usize,
+    failed: bool,
+    ctx: &'c C,
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn trim_parantheses_011() {
-        let tokens_before_trim = tokenize("((x))").unwrap();
-        let tokens = trim_parantheses(&tokens_before_trim);
-
-        let expected = vec!["x"];
-
-        assert_eq!(expected.len(), tokens.len());
-        for (i, token) in tokens.iter().enumerate() {
-            match token {
-                Token::Number(num) => assert_eq!(num, &expected[i].parse::<f64>().unwrap()),
-                Token::Operator(op) => assert_eq!(*op, expected[i]),
-                Token::Variable(v) => assert_eq!(*v, expected[i]),
-                Token::Parenthesis(pa) => assert_eq!(*pa, expected[i]),
-                Token::Trigonometric(tr) => assert_eq!(*tr, expected[i]),
-            }
+impl<'a, 'c, C: Context + ?Sized> Lexer<'a, 'c, C> {
+    pub fn new(expression: &'a str, ctx: &'c C) -> Self {
+        Self {
+            expression,
+            position: 0,
+            split_end: 0,
+            failed: false,
+            ctx,
         }
     }
 
-    #[test]
-    fn trim_parantheses_012() {
-        let tokens_before_trim = tokenize("((3x))").unwrap();
-        let tokens = trim_parantheses(&tokens_before_trim);
-
-        let expected = vec!["3", "x"];
+    /// Advance past the characters matching `accept`, returning the new position.
+    fn skip_while(&mut self, accept: impl Fn(char) -> bool) -> usize {
+        let rest = &self.expression[self.position..];
+        self.position += rest.find(|c: char| !accept(c)).unwrap_or(rest.len());
+        self.position
+    }
 
-        assert_eq!(expected.len(), tokens.len());
-        for (i, token) in tokens.iter().enumerate() {
-            match token {
-                Token::Number(num) => assert_eq!(num, &expected[i].parse::<f64>().unwrap()),
-                Token::Operator(op) => assert_eq!(*op, expected[i]),
-                Token::Variable(v) => assert_eq!(*v, expected[i]),
-                Token::Parenthesis(pa) => assert_eq!(*pa, expected[i]),
-                Token::Trigonometric(tr) => assert_eq!(*tr, expected[i]),
+    ///
+    /// A number literal, decimal with an optional fraction and exponent such as `3`, `.5`,
+    /// `5.` or `6.02e23`, or an integer in hexadecimal `0xFF`, binary `0b1010` or octal `0o17`.
+    /// Digits may be grouped with `_` as in `1_000_000`. A radix prefix needs a digit after
+    /// it and an exponent a digit after its sign, so `0x` still reads as `0*x` and `2e` as `2*e`.
+    ///
+    fn number(&mut self, start: usize) -> Result<Token<'a>, ParseError> {
+        let radix = match &self.expression.as_bytes()[start..] {
+            [b'0', b'x' | b'X', digit, ..] if digit.is_ascii_hexdigit() => Some(16),
+            [b'0', b'b' | b'B', digit, ..] if digit.is_ascii_digit() => Some(2),
+            [b'0', b'o' | b'O', digit, ..] if digit.is_ascii_digit() => Some(8),
+            _ => None,
+        };
+
+        let value = match radix {
+            Some(radix) => {
+                self.position = start + 2;
+                self.skip_while(|c| c.is_ascii_alphanumeric() || c == '_');
+                without_separators(&self.expression[start + 2..self.position])
+                    .and_then(|digits| u64::from_str_radix(&digits, radix).ok())
+                    .map(|value| value as f64)
             }
-        }
+            None => {
+                self.skip_while(|c| c.is_ascii_digit() || c == '_' || c == '.');
+                let exponent = match &self.expression.as_bytes()[self.position..] {
+                    [b'e' | b'E', b'+' | b'-', digit, ..] if digit.is_ascii_digit() => 2,
+                    [b'e' | b'E', digit, ..] if digit.is_ascii_digit() => 1,
+                    _ => 0,
+                };
+                if exponent > 0 {
+                    self.position += exponent;
+                    self.skip_while(|c| c.is_ascii_digit() || c == '_');
+                }
+                without_separators(&self.expression[start..self.position])
+                    .and_then(|literal| literal.parse().ok())
+            }
+        };
+
+        value.map(Token::Number).ok_or_else(|| {
+            ParseError::new(
+                ErrorKind::InvalidNumberParsed,
+                Span::new(start, self.position),
+            )
+        })
     }
 
-    #[test]
-    fn trim_parantheses_02() {
-        let tokens_before_trim = tokenize("(x*3.125)").unwrap();
-        let tokens = trim_parantheses(&tokens_before_trim);
+    ///
+    /// Take an identifier as a function or variable token.
+    ///
+    /// Functions and constants known to the context are taken as a whole. Any other
+    /// alphabetic name of two or more letters directly followed by `(` is a call, left to
+    /// the parser to resolve. Otherwise an identifier starting with a function name is split
+    /// after the longest such name, so `sinx` reads as `sin x` and `cosecx` as `cosec x`,
+    /// and lexing continues with the rest.
+    ///
+    fn identifier(&mut self, start: usize) -> Token<'a> {
+        let end = self.skip_while(|c| c.is_alphabetic() || c.is_ascii_digit() || c == '_');
+        let identifier = &self.expression[start..end];
+        let called = end > self.split_end
+            && self.expression[end..]
+                .trim_start()
+                .starts_with(['(', '[', '{']);
+
+        if self.ctx.function(identifier).is_some() {
+            return Token::Function(identifier);
+        }
 
-        let expected = vec!["x", "*", "3.125"];
+        if self.ctx.constant(identifier).is_some() {
+            return Token::Variable(identifier);
+        }
 
-        assert_eq!(expected.len(), tokens.len());
-        for (i, token) in tokens.iter().enumerate() {
-            match token {
-                Token::Number(num) => assert_eq!(num, &expected[i].parse::<f64>().unwrap()),
-                Token::Operator(op) => assert_eq!(*op, expected[i]),
-                Token::Variable(v) => assert_eq!(*v, expected[i]),
-                Token::Parenthesis(pa) => assert_eq!(*pa, expected[i]),
-                Token::Trigonometric(tr) => assert_eq!(*tr, expected[i]),
+        // a call to a misspelt function such as `sinn(x)` is an unknown function, any other
+        // name before a bracket is a variable multiplying it, as in `f(x)` and `rate (x+1)`
+        let misspelt = || {
+            suggest(identifier, self.ctx.function_names()).is_some_and(|function| {
+                edit_distance(identifier, function) < identifier.chars().count()
+            })
+        };
+        if called && misspelt() {
+            return Token::Function(identifier);
+        }
+
+        // `sinx` and `cosθ2` read as calls, but names such as `cost` and `expected` stay whole
+        let argument = |rest: &str| {
+            let mut chars = rest.chars();
+            chars.next().is_some_and(|c| {
+                matches!(c, 'x' | 'y' | 'z') || (c.is_alphabetic() && !c.is_ascii())
+            }) && chars.all(|c| c.is_ascii_digit())
+        };
+        let function_prefix = identifier
+            .char_indices()
+            .rev()
+            .filter(|(i, _)| *i > 0)
+            .map(|(i, _)| &identifier[..i])
+            .find(|prefix| {
+                self.ctx.function(prefix).is_some() && argument(&identifier[prefix.len()..])
+            });
+
+        match function_prefix {
+            Some(prefix) => {
+                self.split_end = end;
+                self.position = start + prefix.len();
+                Token::Function(prefix)
             }
+            None => Token::Variable(identifier),
         }
     }
+}
 
-    #[test]
-    fn trim_parantheses_03() {
-        let tokens_before_trim = tokenize("((x*3.125))").unwrap();
-        let tokens = trim_parantheses(&tokens_before_trim);
+/// `literal` without its `_` digit separators, or `None` if a separator is not between two digits.
+fn without_separators(literal: &str) -> Option<Cow<'_, str>> {
+    if !literal.contains('_') {
+        return Some(Cow::Borrowed(literal));
+    }
+    let bytes = literal.as_bytes();
+    let separated = |i: usize| {
+        i > 0
+            && i + 1 < bytes.len()
+            && bytes[i - 1].is_ascii_alphanumeric()
+            && bytes[i + 1].is_ascii_alphanumeric()
+    };
+    if bytes
+        .iter()
+        .enumerate()
+        .all(|(i, byte)| *byte != b'_' || separated(i))
+    {
+        Some(Cow::Owned(literal.replace('_', "")))
+    } else {
+        None
+    }
+}
 
-        let expected = vec!["x", "*", "3.125"];
+impl<'a, C: Context + ?Sized> Iterator for Lexer<'a, '_, C> {
+    type Item = Result<SpannedToken<'a>, ParseError>;
 
-        assert_eq!(expected.len(), tokens.len());
-        for (i, token) in tokens.iter().enumerate() {
-            match token {
-                Token::Number(num) => assert_eq!(num, &expected[i].parse::<f64>().unwrap()),
-                Token::Operator(op) => assert_eq!(*op, expected[i]),
-                Token::Variable(v) => assert_eq!(*v, expected[i]),
-                Token::Parenthesis(pa) => assert_eq!(*pa, expected[i]),
-                Token::Trigonometric(tr) => assert_eq!(*tr, expected[i]),
-            }
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
         }
+        let start = self.skip_while(char::is_whitespace);
+        let character = self.expression[start..].chars().next()?;
+
+        let token = match character {
+            '0'..='9' => self.number(start),
+            '.' if self.expression[start + 1..].starts_with(|c: char| c.is_ascii_digit()) => {
+                self.number(start)
+            }
+            c if c.is_alphabetic() || c == '_' => Ok(self.identifier(start)),
+            c => {
+                self.position += c.len_utf8();
+                let text = &self.expression[start..self.position];
+                match c {
+                    '+' | '-' | '*' | '/' | '^' | '%' => Ok(Token::Operator(text)),
+                    '(' | ')' | '[' | ']' | '{' | '}' => Ok(Token::Parenthesis(text)),
+                    ',' => Ok(Token::Comma),
+                    '°' => Ok(Token::Degree),
+                    _ => Err(ParseError::new(
+                        ErrorKind::UnexpectedCharacter(c),
+                        Span::new(start, self.position),
+                    )),
+                }
+            }
+        };
+
+        self.failed = token.is_err();
+        Some(token.map(|token| SpannedToken::new(token, Span::new(start, self.position))))
     }
+}
 
-    #[test]
-    fn trim_parantheses_04() {
-        let tokens_before_trim = tokenize("((3x)+(x^2))").unwrap();
-        let tokens = trim_parantheses(&tokens_before_trim);
+#[cfg(test)]
+#[allow(
+    clippy::assertions_on_constants,
+    clippy::bool_assert_comparison,
+    clippy::useless_vec
+)]
+mod tests {
+    use super::*;
 
-        let expected = vec!["(", "3", "x", ")", "+", "(", "x", "^", "2", ")"];
+    fn tokenize(expression: &str) -> Result<Vec<Token<'_>>, ParseError> {
+        tokenize_with(expression, &())
+            .map(|tokens| tokens.into_iter().map(|spanned| spanned.token).collect())
+    }
 
-        assert_eq!(expected.len(), tokens.len());
-        for (i, token) in tokens.iter().enumerate() {
-            match token {
-                Token::Number(num) => assert_eq!(num, &expected[i].parse::<f64>().unwrap()),
-                Token::Operator(op) => assert_eq!(*op, expected[i]),
-                Token::Variable(v) => assert_eq!(*v, expected[i]),
-                Token::Parenthesis(pa) => assert_eq!(*pa, expected[i]),
-                Token::Trigonometric(tr) => assert_eq!(*tr, expected[i]),
-            }
-        }
+    fn spanned<'a>(tokens: &[Token<'a>]) -> Vec<SpannedToken<'a>> {
+        tokens
+            .iter()
+            .enumerate()
+            .map(|(i, token)| SpannedToken::new(token.clone(), Span::new(i, i + 1)))
+            .collect()
     }
 
     #[test]
@@ -264,7 +481,9 @@ mod tests {
                 Token::Operator(op) => assert_eq!(*op, expected[i]),
                 Token::Variable(v) => assert_eq!(*v, expected[i]),
                 Token::Parenthesis(pa) => assert_eq!(*pa, expected[i]),
-                Token::Trigonometric(tr) => assert_eq!(*tr, expected[i]),
+                Token::Function(tr) => assert_eq!(*tr, expected[i]),
+                Token::Comma => assert_eq!(",", expected[i]),
+                Token::Degree => assert_eq!("°", expected[i]),
             }
         }
     }
@@ -283,7 +502,9 @@ mod tests {
                 Token::Operator(op) => assert_eq!(*op, expected[i]),
                 Token::Variable(v) => assert_eq!(*v, expected[i]),
                 Token::Parenthesis(pa) => assert_eq!(*pa, expected[i]),
-                Token::Trigonometric(tr) => assert_eq!(*tr, expected[i]),
+                Token::Function(tr) => assert_eq!(*tr, expected[i]),
+                Token::Comma => assert_eq!(",", expected[i]),
+                Token::Degree => assert_eq!("°", expected[i]),
             }
         }
     }
@@ -300,7 +521,9 @@ mod tests {
                 Token::Operator(op) => assert_eq!(*op, expected[i]),
                 Token::Variable(v) => assert_eq!(*v, expected[i]),
                 Token::Parenthesis(pa) => assert_eq!(*pa, expected[i]),
-                Token::Trigonometric(tr) => assert_eq!(*tr, expected[i]),
+                Token::Function(tr) => assert_eq!(*tr, expected[i]),
+                Token::Comma => assert_eq!(",", expected[i]),
+                Token::Degree => assert_eq!("°", expected[i]),
             }
         }
     }
@@ -317,7 +540,9 @@ mod tests {
                 Token::Operator(op) => assert_eq!(*op, expected[i]),
                 Token::Variable(v) => assert_eq!(*v, expected[i]),
                 Token::Parenthesis(pa) => assert_eq!(*pa, expected[i]),
-                Token::Trigonometric(tr) => assert_eq!(*tr, expected[i]),
+                Token::Function(tr) => assert_eq!(*tr, expected[i]),
+                Token::Comma => assert_eq!(",", expected[i]),
+                Token::Degree => assert_eq!("°", expected[i]),
             }
         }
     }
@@ -334,30 +559,253 @@ mod tests {
                 Token::Operator(op) => assert_eq!(*op, expected[i]),
                 Token::Variable(v) => assert_eq!(*v, expected[i]),
                 Token::Parenthesis(pa) => assert_eq!(*pa, expected[i]),
-                Token::Trigonometric(tr) => assert_eq!(*tr, expected[i]),
+                Token::Function(tr) => assert_eq!(*tr, expected[i]),
+                Token::Comma => assert_eq!(",", expected[i]),
+                Token::Degree => assert_eq!("°", expected[i]),
+            }
+        }
+    }
+
+    #[test]
+    fn test_tokenize_identifiers() {
+        let expected = ["rate", "*", "time", "+", "x_1", "-", "x2", "+", "θ"];
+        let tokens = tokenize("rate*time + x_1 - x2 + θ").unwrap();
+
+        assert_eq!(expected.len(), tokens.len());
+        for (i, token) in tokens.iter().enumerate() {
+            match token {
+                Token::Variable(v) => assert_eq!(*v, expected[i]),
+                Token::Operator(op) => assert_eq!(*op, expected[i]),
+                _ => panic!(),
             }
         }
     }
 
+    #[test]
+    fn test_tokenize_function_prefix() {
+        let expected = ["sin", "x", "+", "cosec", "y2", "*", "xsin", "-", "tan", "θ"];
+        let tokens = tokenize("sinx + cosecy2 * xsin - tanθ").unwrap();
+
+        assert_eq!(expected.len(), tokens.len());
+        for (i, token) in tokens.iter().enumerate() {
+            match token {
+                Token::Number(num) => assert_eq!(num, &expected[i].parse::<f64>().unwrap()),
+                Token::Operator(op) => assert_eq!(*op, expected[i]),
+                Token::Variable(v) => assert_eq!(*v, expected[i]),
+                Token::Function(tr) => assert_eq!(*tr, expected[i]),
+                Token::Comma => assert_eq!(",", expected[i]),
+                Token::Degree => assert_eq!("°", expected[i]),
+                _ => panic!(),
+            }
+        }
+        assert!(matches!(tokens[0], Token::Function(_)));
+        assert!(matches!(tokens[1], Token::Variable(_)));
+        assert!(matches!(tokens[6], Token::Variable(_)));
+        assert!(matches!(tokens[8], Token::Function(_)));
+    }
+
+    #[test]
+    fn test_tokenize_function_prefix_words() {
+        for name in [
+            "cost", "tank", "secs", "expected", "absolute", "logistic", "sin2x",
+        ] {
+            let tokens = tokenize(name).unwrap();
+            assert_eq!(tokens.len(), 1, "{}", name);
+            assert!(
+                matches!(tokens[0], Token::Variable(v) if v == name),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_tokenize_call() {
+        let expected = [
+            "sqr", "(", "x", ",", "0", ",", "1", ")", "+", "y", "(", "x", ")",
+        ];
+        let tokens = tokenize("sqr(x, 0, 1) + y(x)").unwrap();
+
+        assert_eq!(expected.len(), tokens.len());
+        for (i, token) in tokens.iter().enumerate() {
+            match token {
+                Token::Number(num) => assert_eq!(num, &expected[i].parse::<f64>().unwrap()),
+                Token::Operator(op) => assert_eq!(*op, expected[i]),
+                Token::Variable(v) => assert_eq!(*v, expected[i]),
+                Token::Parenthesis(pa) => assert_eq!(*pa, expected[i]),
+                Token::Function(tr) => assert_eq!(*tr, expected[i]),
+                Token::Comma => assert_eq!(",", expected[i]),
+                Token::Degree => assert_eq!("°", expected[i]),
+            }
+        }
+        assert!(matches!(tokens[0], Token::Function(_)));
+        assert!(matches!(tokens[9], Token::Variable(_)));
+    }
+
     #[test]
     fn test_validate_ok() {
-        let tokens = vec![
+        let tokens = spanned(&[
             Token::Parenthesis("("),
             Token::Variable("x"),
             Token::Parenthesis(")"),
-        ];
+        ]);
         let tokens_ref = tokens.as_slice();
         assert_eq!(validate(tokens_ref).is_ok(), true);
     }
 
     #[test]
     fn test_validate_err() {
-        let tokens = vec![
+        let tokens = spanned(&[
             Token::Parenthesis(")"),
             Token::Operator("+"),
             Token::Parenthesis("("),
-        ];
+        ]);
         let tokens_ref = tokens.as_slice();
-        assert_eq!(validate(tokens_ref).is_ok(), false);
+        let error = validate(tokens_ref).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::UnmatchedBracket(')'));
+        assert_eq!(error.span, Span::new(0, 1));
+    }
+
+    #[test]
+    fn test_validate_nested() {
+        let error =
+            |expression: &str| validate(&tokenize_with(expression, &()).unwrap()).unwrap_err();
+
+        assert!(validate(&tokenize_with("((x)+[y*{2}])", &()).unwrap()).is_ok());
+
+        let unclosed = error("((x)");
+        assert_eq!(unclosed.kind, ErrorKind::UnclosedBracket('('));
+        assert_eq!(unclosed.span, Span::new(0, 1));
+        assert_eq!(unclosed.expected, Some(Expected::ClosingBracket(')')));
+
+        let unmatched = error("(x))(");
+        assert_eq!(unmatched.kind, ErrorKind::UnmatchedBracket(')'));
+        assert_eq!(unmatched.span, Span::new(3, 4));
+
+        let mismatched = error("[x+(y])");
+        assert_eq!(
+            mismatched.kind,
+            ErrorKind::MismatchedBracket {
+                open: '(',
+                close: ']'
+            }
+        );
+        assert_eq!(mismatched.span, Span::new(5, 6));
+        assert_eq!(mismatched.expected, Some(Expected::ClosingBracket(')')));
+
+        assert_eq!(error("{x").kind, ErrorKind::UnclosedBracket('{'));
+        assert_eq!(error("()").kind, ErrorKind::Empty);
+    }
+
+    #[test]
+    fn test_tokenize_unexpected() {
+        for (expression, character, start) in
+            [("x $ 2", '$', 2), ("x & 1", '&', 2), ("x . 1", '.', 2)]
+        {
+            let error = tokenize(expression).err().unwrap();
+            assert_eq!(error.kind, ErrorKind::UnexpectedCharacter(character));
+            assert_eq!(error.span, Span::new(start, start + 1));
+        }
+        let error = tokenize("θ + ∑x").err().unwrap();
+        assert_eq!(error.kind, ErrorKind::UnexpectedCharacter('∑'));
+        assert_eq!(error.span, Span::new(5, 8));
+    }
+
+    #[test]
+    fn test_tokenize_numbers() {
+        for (literal, value) in [
+            ("1e-3", 0.001),
+            ("1E+3", 1000.0),
+            (".5", 0.5),
+            ("5.", 5.0),
+            ("6.02e23", 6.02e23),
+            ("0xFF", 255.0),
+            ("0Xff", 255.0),
+            ("0b1010", 10.0),
+            ("0o17", 15.0),
+            ("1_000_000", 1_000_000.0),
+            ("0xFF_FF", 65535.0),
+            ("1_000.000_1", 1000.0001),
+        ] {
+            let tokens = tokenize(literal).unwrap();
+            assert_eq!(tokens.len(), 1, "{}", literal);
+            assert!(
+                matches!(tokens[0], Token::Number(num) if num == value),
+                "{}",
+                literal
+            );
+        }
+
+        assert!(matches!(
+            tokenize("0x").unwrap()[..],
+            [Token::Number(_), Token::Variable("x")]
+        ));
+        assert!(matches!(
+            tokenize("2e").unwrap()[..],
+            [Token::Number(_), Token::Variable("e")]
+        ));
+        assert!(matches!(
+            tokenize("2e-x").unwrap()[..],
+            [
+                Token::Number(_),
+                Token::Variable("e"),
+                Token::Operator("-"),
+                Token::Variable("x")
+            ]
+        ));
+
+        for (expression, span) in [
+            ("1.2.3", Span::new(0, 5)),
+            ("x + 0b102", Span::new(4, 9)),
+            ("0o8", Span::new(0, 3)),
+            ("0xFG", Span::new(0, 4)),
+            ("1__0", Span::new(0, 4)),
+            ("2 * 10_", Span::new(4, 7)),
+            ("1_.5", Span::new(0, 4)),
+        ] {
+            let error = tokenize(expression).err().unwrap();
+            assert_eq!(error.kind, ErrorKind::InvalidNumberParsed, "{}", expression);
+            assert_eq!(error.span, span, "{}", expression);
+        }
+    }
+
+    #[test]
+    fn test_lexer() {
+        let mut lexer = Lexer::new("2 $ 3", &());
+        assert!(matches!(
+            lexer.next(),
+            Some(Ok(SpannedToken {
+                token: Token::Number(_),
+                ..
+            }))
+        ));
+        assert!(matches!(lexer.next(), Some(Err(_))));
+        assert!(lexer.next().is_none());
+
+        let expected = ["sin", "x2", "(", "x", ")"];
+        let tokens = tokenize("sinx2(x)").unwrap();
+        assert_eq!(expected.len(), tokens.len());
+        assert!(matches!(tokens[1], Token::Variable("x2")));
+        assert!(matches!(tokens[2], Token::Parenthesis("(")));
+    }
+
+    #[test]
+    fn test_tokenize_spans() {
+        let tokens = tokenize_with("sinx + θ2*30°", &()).unwrap();
+        let spans: Vec<Span> = tokens.iter().map(|spanned| spanned.span).collect();
+        assert_eq!(
+            spans,
+            [
+                Span::new(0, 3),
+                Span::new(3, 4),
+                Span::new(5, 6),
+                Span::new(7, 10),
+                Span::new(10, 11),
+                Span::new(11, 13),
+                Span::new(13, 15),
+            ]
+        );
+        assert_eq!(Span::new(7, 10).char_range("sinx + θ2*30°"), 7..9);
+        assert_eq!(Span::new(13, 15).char_range("sinx + θ2*30°"), 12..13);
     }
 }
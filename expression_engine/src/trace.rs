@@ -0,0 +1,88 @@
+//!
+//! Debug events of parsing and evaluation. With the `tracing` feature they are emitted
+//! through `tracing` at debug level, without it they are compiled out entirely.
+//!
+
+use crate::node::{EvalError, ExpressionNode};
+use crate::token::ParseError;
+use std::fmt::Display;
+
+#[cfg(feature = "tracing")]
+pub(crate) fn parsed(expression: &str, parsed: &Result<ExpressionNode, ParseError>) {
+    match parsed {
+        Ok(root_node) => tracing::debug!(expression, parsed = %root_node, "parsed expression"),
+        Err(error) => tracing::debug!(
+            expression,
+            error = ?error.kind,
+            start = error.span.start,
+            end = error.span.end,
+            "failed to parse expression"
+        ),
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+#[inline(always)]
+pub(crate) fn parsed(_expression: &str, _parsed: &Result<ExpressionNode, ParseError>) {}
+
+#[cfg(feature = "tracing")]
+pub(crate) fn evaluated(node: &impl Display, result: &Result<f64, EvalError>) {
+    match result {
+        Ok(value) => tracing::debug!(expression = %node, value, "evaluated expression"),
+        Err(error) => tracing::debug!(
+            expression = %node,
+            error = ?error.kind,
+            "failed to evaluate expression"
+        ),
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+#[inline(always)]
+pub(crate) fn evaluated(_node: &impl Display, _result: &Result<f64, EvalError>) {}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use crate::parser::parse;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Level, Metadata, Subscriber};
+
+    /// Counts the debug events it receives.
+    struct CountEvents(Arc<AtomicUsize>);
+
+    impl Subscriber for CountEvents {
+        fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+            *metadata.level() == Level::DEBUG
+        }
+
+        fn new_span(&self, _span: &Attributes<'_>) -> Id {
+            Id::from_u64(1)
+        }
+
+        fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+        fn event(&self, _event: &Event<'_>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn enter(&self, _span: &Id) {}
+
+        fn exit(&self, _span: &Id) {}
+    }
+
+    #[test]
+    fn debug_events() {
+        let count = Arc::new(AtomicUsize::new(0));
+        tracing::subscriber::with_default(CountEvents(count.clone()), || {
+            let exp = parse("x^2 + 1").unwrap();
+            assert_eq!(exp.evaluate("x", 2.0).unwrap(), 5.0);
+            assert!(parse("x +").is_err());
+            assert!(exp.evaluate("y", 2.0).is_err());
+        });
+        assert_eq!(count.load(Ordering::SeqCst), 4);
+    }
+}
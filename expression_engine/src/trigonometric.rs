@@ -1,6 +1,47 @@
+use crate::node::{EvalError, EvalErrorKind};
 use std::fmt;
 
-#[derive(Clone)]
+/// Distance from zero within which a sine or cosine counts as a pole of its reciprocal.
+const POLE_TOLERANCE: f64 = 1e-12;
+
+/// Unit in which trigonometric functions take and inverse trigonometric functions return angles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AngleMode {
+    #[default]
+    Radians,
+    Degrees,
+    /// 400 gradians to the full turn.
+    Gradians,
+}
+
+impl AngleMode {
+    pub fn to_radians(&self, angle: f64) -> f64 {
+        match self {
+            AngleMode::Radians => angle,
+            AngleMode::Degrees => angle.to_radians(),
+            AngleMode::Gradians => angle * std::f64::consts::PI / 200.0,
+        }
+    }
+
+    pub fn from_radians(&self, radians: f64) -> f64 {
+        match self {
+            AngleMode::Radians => radians,
+            AngleMode::Degrees => radians.to_degrees(),
+            AngleMode::Gradians => radians * 200.0 / std::f64::consts::PI,
+        }
+    }
+
+    /// Convert an angle in degrees, as written with a `°` suffix, to this unit.
+    pub fn from_degrees(&self, degrees: f64) -> f64 {
+        match self {
+            AngleMode::Radians => degrees.to_radians(),
+            AngleMode::Degrees => degrees,
+            AngleMode::Gradians => degrees * 400.0 / 360.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TrigonometricFunction {
     Sin,
     Cos,
@@ -8,21 +49,97 @@ pub enum TrigonometricFunction {
     Sec,
     Cosec,
     Cot,
+    Asin,
+    Acos,
+    Atan,
+    Asec,
+    Acsc,
+    Acot,
+    Sinh,
+    Cosh,
+    Tanh,
+    Asinh,
+    Acosh,
+    Atanh,
 }
 
 impl TrigonometricFunction {
+    pub const ALL: [TrigonometricFunction; 18] = [
+        TrigonometricFunction::Sin,
+        TrigonometricFunction::Cos,
+        TrigonometricFunction::Tan,
+        TrigonometricFunction::Sec,
+        TrigonometricFunction::Cosec,
+        TrigonometricFunction::Cot,
+        TrigonometricFunction::Asin,
+        TrigonometricFunction::Acos,
+        TrigonometricFunction::Atan,
+        TrigonometricFunction::Asec,
+        TrigonometricFunction::Acsc,
+        TrigonometricFunction::Acot,
+        TrigonometricFunction::Sinh,
+        TrigonometricFunction::Cosh,
+        TrigonometricFunction::Tanh,
+        TrigonometricFunction::Asinh,
+        TrigonometricFunction::Acosh,
+        TrigonometricFunction::Atanh,
+    ];
+
+    /// Alternative spellings accepted by [`TrigonometricFunction::from`].
+    pub const ALIASES: [(&'static str, TrigonometricFunction); 1] =
+        [("csc", TrigonometricFunction::Cosec)];
+
     pub fn from(token_str: &str) -> Option<TrigonometricFunction> {
         match token_str {
             "sin" => Some(TrigonometricFunction::Sin),
             "cos" => Some(TrigonometricFunction::Cos),
             "tan" => Some(TrigonometricFunction::Tan),
             "sec" => Some(TrigonometricFunction::Sec),
-            "cosec" => Some(TrigonometricFunction::Cosec),
+            "cosec" | "csc" => Some(TrigonometricFunction::Cosec),
             "cot" => Some(TrigonometricFunction::Cot),
+            "asin" => Some(TrigonometricFunction::Asin),
+            "acos" => Some(TrigonometricFunction::Acos),
+            "atan" => Some(TrigonometricFunction::Atan),
+            "asec" => Some(TrigonometricFunction::Asec),
+            "acsc" => Some(TrigonometricFunction::Acsc),
+            "acot" => Some(TrigonometricFunction::Acot),
+            "sinh" => Some(TrigonometricFunction::Sinh),
+            "cosh" => Some(TrigonometricFunction::Cosh),
+            "tanh" => Some(TrigonometricFunction::Tanh),
+            "asinh" => Some(TrigonometricFunction::Asinh),
+            "acosh" => Some(TrigonometricFunction::Acosh),
+            "atanh" => Some(TrigonometricFunction::Atanh),
             _ => None,
         }
     }
 
+    pub fn name(&self) -> &'static str {
+        match self {
+            TrigonometricFunction::Sin => "sin",
+            TrigonometricFunction::Cos => "cos",
+            TrigonometricFunction::Tan => "tan",
+            TrigonometricFunction::Sec => "sec",
+            TrigonometricFunction::Cosec => "cosec",
+            TrigonometricFunction::Cot => "cot",
+            TrigonometricFunction::Asin => "asin",
+            TrigonometricFunction::Acos => "acos",
+            TrigonometricFunction::Atan => "atan",
+            TrigonometricFunction::Asec => "asec",
+            TrigonometricFunction::Acsc => "acsc",
+            TrigonometricFunction::Acot => "acot",
+            TrigonometricFunction::Sinh => "sinh",
+            TrigonometricFunction::Cosh => "cosh",
+            TrigonometricFunction::Tanh => "tanh",
+            TrigonometricFunction::Asinh => "asinh",
+            TrigonometricFunction::Acosh => "acosh",
+            TrigonometricFunction::Atanh => "atanh",
+        }
+    }
+
+    ///
+    /// Evaluate in radians. The inverse reciprocal functions follow the principal values of
+    /// their reciprocals, `asec(x) = acos(1/x)`, `acsc(x) = asin(1/x)` and `acot(x) = atan(1/x)`.
+    ///
     pub fn evaluate(&self, value: f64) -> f64 {
         match self {
             TrigonometricFunction::Sin => value.sin(),
@@ -31,20 +148,191 @@ impl TrigonometricFunction {
             TrigonometricFunction::Sec => 1.0 / value.cos(),
             TrigonometricFunction::Cosec => 1.0 / value.sin(),
             TrigonometricFunction::Cot => 1.0 / value.tan(),
+            TrigonometricFunction::Asin => value.asin(),
+            TrigonometricFunction::Acos => value.acos(),
+            TrigonometricFunction::Atan => value.atan(),
+            TrigonometricFunction::Asec => (1.0 / value).acos(),
+            TrigonometricFunction::Acsc => (1.0 / value).asin(),
+            TrigonometricFunction::Acot => (1.0 / value).atan(),
+            TrigonometricFunction::Sinh => value.sinh(),
+            TrigonometricFunction::Cosh => value.cosh(),
+            TrigonometricFunction::Tanh => value.tanh(),
+            TrigonometricFunction::Asinh => value.asinh(),
+            TrigonometricFunction::Acosh => value.acosh(),
+            TrigonometricFunction::Atanh => value.atanh(),
+        }
+    }
+
+    ///
+    /// Evaluate with angles in `mode`: the argument of the trigonometric functions and the
+    /// result of the inverse trigonometric functions are in `mode` units. Hyperbolic
+    /// functions do not take angles and evaluate as with [`TrigonometricFunction::evaluate`].
+    ///
+    pub fn evaluate_in(&self, value: f64, mode: AngleMode) -> f64 {
+        match self {
+            TrigonometricFunction::Sin
+            | TrigonometricFunction::Cos
+            | TrigonometricFunction::Tan
+            | TrigonometricFunction::Sec
+            | TrigonometricFunction::Cosec
+            | TrigonometricFunction::Cot => self.evaluate(mode.to_radians(value)),
+            TrigonometricFunction::Asin
+            | TrigonometricFunction::Acos
+            | TrigonometricFunction::Atan
+            | TrigonometricFunction::Asec
+            | TrigonometricFunction::Acsc
+            | TrigonometricFunction::Acot => mode.from_radians(self.evaluate(value)),
+            _ => self.evaluate(value),
+        }
+    }
+
+    ///
+    /// Whether the function is defined for `value` in `mode`: `tan` and `sec` have poles
+    /// where the cosine is zero, `cot` and `cosec` where the sine is, and the inverse
+    /// functions are only real on part of the line, such as `asin` on `[-1, 1]`.
+    ///
+    pub fn in_domain(&self, value: f64, mode: AngleMode) -> bool {
+        match self {
+            TrigonometricFunction::Tan | TrigonometricFunction::Sec => {
+                mode.to_radians(value).cos().abs() > POLE_TOLERANCE
+            }
+            TrigonometricFunction::Cosec | TrigonometricFunction::Cot => {
+                mode.to_radians(value).sin().abs() > POLE_TOLERANCE
+            }
+            TrigonometricFunction::Asin | TrigonometricFunction::Acos => value.abs() <= 1.0,
+            TrigonometricFunction::Asec | TrigonometricFunction::Acsc => value.abs() >= 1.0,
+            TrigonometricFunction::Acosh => value >= 1.0,
+            TrigonometricFunction::Atanh => value.abs() < 1.0,
+            _ => true,
+        }
+    }
+
+    ///
+    /// Evaluate as with [`TrigonometricFunction::evaluate_in`], failing with
+    /// [`EvalErrorKind::DomainError`] for a value outside [`TrigonometricFunction::in_domain`].
+    ///
+    pub fn evaluate_checked(&self, value: f64, mode: AngleMode) -> Result<f64, EvalError> {
+        if self.in_domain(value, mode) {
+            Ok(self.evaluate_in(value, mode))
+        } else {
+            Err(EvalError {
+                kind: EvalErrorKind::DomainError {
+                    function: self.name().to_string(),
+                    value,
+                },
+            })
         }
     }
 }
 
 impl fmt::Display for TrigonometricFunction {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match &self {
-            TrigonometricFunction::Sin => write!(f, "sin")?,
-            TrigonometricFunction::Cos => write!(f, "cos")?,
-            TrigonometricFunction::Tan => write!(f, "tan")?,
-            TrigonometricFunction::Sec => write!(f, "sec")?,
-            TrigonometricFunction::Cosec => write!(f, "cosec")?,
-            TrigonometricFunction::Cot => write!(f, "cot")?,
-        }
+        write!(f, "{}", self.name())?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    const EPSILON: f64 = 1e-12;
+
+    #[test]
+    fn evaluate_inverse() {
+        assert!((TrigonometricFunction::Asin.evaluate(1.0) - PI / 2.0).abs() < EPSILON);
+        assert!((TrigonometricFunction::Acos.evaluate(-1.0) - PI).abs() < EPSILON);
+        assert!((TrigonometricFunction::Atan.evaluate(1.0) - PI / 4.0).abs() < EPSILON);
+        assert!((TrigonometricFunction::Asec.evaluate(2.0) - PI / 3.0).abs() < EPSILON);
+        assert!((TrigonometricFunction::Acsc.evaluate(2.0) - PI / 6.0).abs() < EPSILON);
+        assert!((TrigonometricFunction::Acot.evaluate(1.0) - PI / 4.0).abs() < EPSILON);
+        assert!((TrigonometricFunction::Acot.evaluate(0.0) - PI / 2.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn evaluate_hyperbolic() {
+        let x: f64 = 0.5;
+        assert!(
+            (TrigonometricFunction::Sinh.evaluate(x) - (x.exp() - (-x).exp()) / 2.0).abs()
+                < EPSILON
+        );
+        assert!(
+            (TrigonometricFunction::Cosh.evaluate(x) - (x.exp() + (-x).exp()) / 2.0).abs()
+                < EPSILON
+        );
+        assert!((TrigonometricFunction::Tanh.evaluate(x) - x.sinh() / x.cosh()).abs() < EPSILON);
+        assert!((TrigonometricFunction::Asinh.evaluate(x.sinh()) - x).abs() < EPSILON);
+        assert!((TrigonometricFunction::Acosh.evaluate(x.cosh()) - x).abs() < EPSILON);
+        assert!((TrigonometricFunction::Atanh.evaluate(x.tanh()) - x).abs() < EPSILON);
+    }
+
+    #[test]
+    fn evaluate_angle_mode() {
+        let sin = TrigonometricFunction::Sin;
+        assert!((sin.evaluate_in(PI / 6.0, AngleMode::Radians) - 0.5).abs() < EPSILON);
+        assert!((sin.evaluate_in(30.0, AngleMode::Degrees) - 0.5).abs() < EPSILON);
+        assert!((sin.evaluate_in(100.0, AngleMode::Gradians) - 1.0).abs() < EPSILON);
+        assert!(
+            (TrigonometricFunction::Acos.evaluate_in(0.0, AngleMode::Degrees) - 90.0).abs()
+                < EPSILON
+        );
+        assert!(
+            (TrigonometricFunction::Atan.evaluate_in(1.0, AngleMode::Gradians) - 50.0).abs()
+                < EPSILON
+        );
+        assert_eq!(
+            TrigonometricFunction::Sinh.evaluate_in(1.0, AngleMode::Degrees),
+            1.0f64.sinh()
+        );
+    }
+
+    #[test]
+    fn angle_mode_conversions() {
+        assert_eq!(AngleMode::Radians.from_degrees(180.0), PI);
+        assert_eq!(AngleMode::Degrees.from_degrees(45.0), 45.0);
+        assert_eq!(AngleMode::Gradians.from_degrees(90.0), 100.0);
+        assert_eq!(AngleMode::Degrees.to_radians(180.0), PI);
+        assert_eq!(AngleMode::Gradians.from_radians(PI), 200.0);
+        assert_eq!(AngleMode::default(), AngleMode::Radians);
+    }
+
+    #[test]
+    fn trigonometric_domain() {
+        let tan = TrigonometricFunction::Tan;
+        assert!(!tan.in_domain(PI / 2.0, AngleMode::Radians));
+        assert!(!tan.in_domain(-270.0, AngleMode::Degrees));
+        assert!(tan.in_domain(1.0, AngleMode::Radians));
+        assert!(!TrigonometricFunction::Cot.in_domain(0.0, AngleMode::Radians));
+        assert!(!TrigonometricFunction::Cosec.in_domain(200.0, AngleMode::Gradians));
+        assert!(!TrigonometricFunction::Asin.in_domain(1.5, AngleMode::Radians));
+        assert!(TrigonometricFunction::Acos.in_domain(-1.0, AngleMode::Degrees));
+        assert!(!TrigonometricFunction::Asec.in_domain(0.5, AngleMode::Radians));
+        assert!(!TrigonometricFunction::Acosh.in_domain(0.0, AngleMode::Radians));
+        assert!(!TrigonometricFunction::Atanh.in_domain(1.0, AngleMode::Radians));
+        assert!(TrigonometricFunction::Sinh.in_domain(1e3, AngleMode::Radians));
+
+        assert_eq!(
+            TrigonometricFunction::Asin
+                .evaluate_checked(2.0, AngleMode::Radians)
+                .unwrap_err()
+                .kind,
+            EvalErrorKind::DomainError {
+                function: String::from("asin"),
+                value: 2.0
+            }
+        );
+        assert!((tan.evaluate_checked(45.0, AngleMode::Degrees).unwrap() - 1.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn trigonometric_from_name() {
+        for trig_fn in TrigonometricFunction::ALL {
+            assert_eq!(TrigonometricFunction::from(trig_fn.name()), Some(trig_fn));
+        }
+        for (alias, trig_fn) in TrigonometricFunction::ALIASES {
+            assert_eq!(TrigonometricFunction::from(alias), Some(trig_fn));
+        }
+        assert_eq!(TrigonometricFunction::from("ln"), None);
+    }
+}
@@ -0,0 +1,231 @@
+//!
+//! Traversal of [`Expr`] trees. Implement only the methods for the node kinds of interest,
+//! the defaults visit or rebuild every other node and recurse into its children.
+//!
+
+use crate::expr::{Expr, UnaryOperator};
+use crate::operator::Operator;
+
+///
+/// Read-only walk over an [`Expr`] tree.
+///
+/// [`Visitor::visit_expr`] dispatches to the method for the kind of node, whose default
+/// visits the children, so overriding one of them and calling [`walk_expr`] or the children
+/// keeps the traversal going.
+///
+/// # Examples
+///
+/// ```
+/// use expression_engine::{parse_expr, Visitor};
+///
+/// #[derive(Default)]
+/// struct Variables(Vec<String>);
+///
+/// impl Visitor for Variables {
+///     fn visit_variable(&mut self, name: &str) {
+///         self.0.push(name.to_string());
+///     }
+/// }
+///
+/// let mut variables = Variables::default();
+/// parse_expr("x^2 + sin(y) * pi").unwrap().visit(&mut variables);
+/// assert_eq!(variables.0, ["x", "y"]);
+/// ```
+///
+pub trait Visitor {
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr)
+    }
+
+    fn visit_number(&mut self, _value: f64, _literal: Option<&str>) {}
+
+    fn visit_variable(&mut self, _name: &str) {}
+
+    fn visit_constant(&mut self, _name: &str, _value: f64) {}
+
+    fn visit_unary(&mut self, _op: UnaryOperator, operand: &Expr) {
+        self.visit_expr(operand)
+    }
+
+    fn visit_binary(&mut self, _op: Operator, left: &Expr, right: &Expr) {
+        self.visit_expr(left);
+        self.visit_expr(right)
+    }
+
+    fn visit_call(&mut self, _name: &str, args: &[Expr]) {
+        for arg in args {
+            self.visit_expr(arg)
+        }
+    }
+}
+
+/// Dispatch `expr` to the method of `visitor` for its kind of node.
+pub fn walk_expr<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expr) {
+    match expr {
+        Expr::Number { value, literal } => visitor.visit_number(*value, literal.as_deref()),
+        Expr::Variable(name) => visitor.visit_variable(name),
+        Expr::Constant { name, value } => visitor.visit_constant(name, *value),
+        Expr::Unary { op, operand } => visitor.visit_unary(*op, operand),
+        Expr::Binary { op, left, right } => visitor.visit_binary(*op, left, right),
+        Expr::Call { name, args } => visitor.visit_call(name, args),
+    }
+}
+
+///
+/// Transformation of an [`Expr`] tree into a new one, consuming it.
+///
+/// [`Folder::fold_expr`] dispatches to the method for the kind of node, whose default
+/// rebuilds the node from its folded children, so a folder changes only what it overrides.
+///
+/// # Examples
+///
+/// ```
+/// use expression_engine::{parse_expr, Expr, Folder};
+///
+/// /// Replaces named constants by their value.
+/// struct InlineConstants;
+///
+/// impl Folder for InlineConstants {
+///     fn fold_constant(&mut self, _name: String, value: f64) -> Expr {
+///         Expr::Number { value, literal: None }
+///     }
+/// }
+///
+/// let expr = parse_expr("2*pi*r").unwrap().fold(&mut InlineConstants);
+/// assert_eq!(expr.to_string(), "2*3.141592653589793*r");
+/// ```
+///
+pub trait Folder {
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        fold_expr(self, expr)
+    }
+
+    fn fold_number(&mut self, value: f64, literal: Option<String>) -> Expr {
+        Expr::Number { value, literal }
+    }
+
+    fn fold_variable(&mut self, name: String) -> Expr {
+        Expr::Variable(name)
+    }
+
+    fn fold_constant(&mut self, name: String, value: f64) -> Expr {
+        Expr::Constant { name, value }
+    }
+
+    fn fold_unary(&mut self, op: UnaryOperator, operand: Expr) -> Expr {
+        Expr::Unary {
+            op,
+            operand: Box::new(self.fold_expr(operand)),
+        }
+    }
+
+    fn fold_binary(&mut self, op: Operator, left: Expr, right: Expr) -> Expr {
+        Expr::Binary {
+            op,
+            left: Box::new(self.fold_expr(left)),
+            right: Box::new(self.fold_expr(right)),
+        }
+    }
+
+    fn fold_call(&mut self, name: String, args: Vec<Expr>) -> Expr {
+        Expr::Call {
+            name,
+            args: args.into_iter().map(|arg| self.fold_expr(arg)).collect(),
+        }
+    }
+}
+
+/// Dispatch `expr` to the method of `folder` for its kind of node.
+pub fn fold_expr<F: Folder + ?Sized>(folder: &mut F, expr: Expr) -> Expr {
+    match expr {
+        Expr::Number { value, literal } => folder.fold_number(value, literal),
+        Expr::Variable(name) => folder.fold_variable(name),
+        Expr::Constant { name, value } => folder.fold_constant(name, value),
+        Expr::Unary { op, operand } => folder.fold_unary(op, *operand),
+        Expr::Binary { op, left, right } => folder.fold_binary(op, *left, *right),
+        Expr::Call { name, args } => folder.fold_call(name, args),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse, parse_expr};
+
+    /// Counts the nodes of each kind.
+    #[derive(Default)]
+    struct CountNodes {
+        leaves: usize,
+        operators: usize,
+        calls: usize,
+    }
+
+    impl Visitor for CountNodes {
+        fn visit_expr(&mut self, expr: &Expr) {
+            match expr {
+                Expr::Unary { .. } | Expr::Binary { .. } => self.operators += 1,
+                Expr::Call { .. } => self.calls += 1,
+                _ => self.leaves += 1,
+            }
+            walk_expr(self, expr)
+        }
+    }
+
+    /// Substitutes an expression for a variable.
+    struct Substitute<'a> {
+        variable: &'a str,
+        replacement: Expr,
+    }
+
+    impl Folder for Substitute<'_> {
+        fn fold_variable(&mut self, name: String) -> Expr {
+            if name == self.variable {
+                self.replacement.clone()
+            } else {
+                Expr::Variable(name)
+            }
+        }
+    }
+
+    #[test]
+    fn visit_every_node() {
+        let mut count = CountNodes::default();
+        parse_expr("-x^2 + atan2(y, 2) * sin(30°)")
+            .unwrap()
+            .visit(&mut count);
+        assert_eq!(count.leaves, 5);
+        assert_eq!(count.operators, 5);
+        assert_eq!(count.calls, 2);
+    }
+
+    #[test]
+    fn visit_legacy_node() {
+        let mut count = CountNodes::default();
+        parse("(x+1)(x-1)").unwrap().visit(&mut count);
+        assert_eq!(count.leaves, 4);
+        assert_eq!(count.operators, 3);
+    }
+
+    #[test]
+    fn fold_substitute() {
+        let mut substitute = Substitute {
+            variable: "x",
+            replacement: parse_expr("y + 1").unwrap(),
+        };
+        let expr = parse_expr("x^2 - log(2, x)").unwrap().fold(&mut substitute);
+        assert_eq!(expr.to_string(), "(y+1)^2-log(2,y+1)");
+        assert_eq!(expr.evaluate("y", 1.0).unwrap(), 3.0);
+
+        let node = parse("3x").unwrap().fold(&mut substitute);
+        assert_eq!(node.evaluate("y", 1.0).unwrap(), 6.0);
+    }
+
+    #[test]
+    fn fold_default_is_identity() {
+        struct Identity;
+        impl Folder for Identity {}
+
+        let expr = parse_expr("-(0x1F + e) % cosh(x)°").unwrap();
+        assert_eq!(expr.clone().fold(&mut Identity), expr);
+    }
+}
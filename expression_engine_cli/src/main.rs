@@ -1,6 +1,6 @@
-use std::env::{self, Args};
+use std::env;
 
-use expression_engine::{parse, ExpressionNode, ExpressionNodeType, Operator};
+use expression_engine::{parse, Diagnostic, Environment, EvalErrorKind, EvalMode};
 
 fn main() {
     let args: Vec<String> = env::args().collect();
@@ -10,21 +10,30 @@ fn main() {
     match parse(exp) {
         Ok(exp) => {
             println!("Expression {}", exp);
-            let res = exp.evaluate("x", args[2].parse::<f64>().unwrap());
+            let mut env = Environment::new();
+            env.set("x", args[2].parse::<f64>().unwrap());
+            env.set_eval_mode(EvalMode::Strict);
+            let res = exp.evaluate_with(&env);
             match res {
                 Ok(value) => println!("Evaluation Result {}", value),
-                Err(_) => todo!(),
+                Err(error) => match error.kind {
+                    EvalErrorKind::NoSubstitute(name) => {
+                        println!("No value given for variable {}", name)
+                    }
+                    EvalErrorKind::UnknownFunction(name) => println!("Unknown function {}", name),
+                    EvalErrorKind::DomainError { function, value } => {
+                        println!("{} is not defined for {}", function, value)
+                    }
+                    EvalErrorKind::DivisionByZero { expression } => {
+                        println!("Division by zero in {}", expression)
+                    }
+                    EvalErrorKind::NonFinite { expression } => {
+                        println!("{} does not evaluate to a finite number", expression)
+                    }
+                    _ => println!("Failed to evaluate expression"),
+                },
             }
         }
-        Err(error) => match error.kind {
-            expression_engine::ErrorKind::Empty => println!("Empty expression"),
-            expression_engine::ErrorKind::InvalidOpenCloseParantheses => {
-                println!("Incorrect number of open/close parantheses")
-            }
-            expression_engine::ErrorKind::InvalidNumberParsed => {
-                println!("Failed to parse number(s) in expression")
-            }
-            expression_engine::ErrorKind::InvalidExpression => println!("Expression is invalid"),
-        },
+        Err(error) => println!("{}", Diagnostic::new(exp, &error)),
     }
 }
@@ -77,21 +77,24 @@ impl LayerDrawing for GraphLayer<'_> {
         let current_size = vertices.len();
         let width = self.width as i32;
         let height = self.height as i32;
-        for px in 0..=self.width {
-            // Map pixel (px) to normalized device coordinates (NDC) for x
-            let x_ndc = (px as f64 / width as f64) * 2.0 - 1.0;
+        // Map pixels (px) to normalized device coordinates (NDC) for x
+        let xs_ndc: Vec<f64> = (0..=self.width)
+            .map(|px| (px as f64 / width as f64) * 2.0 - 1.0)
+            .collect();
 
-            // Compute y based on the mathematical expression
-            match self.expression.evaluate("x", (x_ndc as f64) / self.scale) {
-                Ok(eval) => {
+        // Compute y for every pixel at once based on the mathematical expression
+        let xs: Vec<f64> = xs_ndc.iter().map(|x_ndc| x_ndc / self.scale).collect();
+        let mut ys = vec![0.0; xs.len()];
+        if let Ok(errors) = self.expression.evaluate_many("x", &xs, &mut ys) {
+            let mask = errors.mask(ys.len());
+            for ((x_ndc, eval), valid) in xs_ndc.iter().zip(&ys).zip(mask) {
+                if valid {
+                    // Map y to NDC (already in -1 to 1 range)
                     let y = ((eval * ((width as f64) / height as f64)) * self.scale) as f32;
-                    vertices.push(x_ndc as f32);
+                    vertices.push(*x_ndc as f32);
                     vertices.push(y);
                 }
-                Err(_) => (),
-            };
-
-            // Map y to NDC (already in -1 to 1 range)
+            }
         }
 
         DrawLayer {
@@ -4,7 +4,7 @@ use draw_layer::{DrawLayer, GraphLayer, LayerDrawing, XAxisLayer, YAxisLayer};
 use std::cell::RefCell;
 use std::rc::Rc;
 
-use expression_engine::{parse, ExpressionNode, ExpressionNodeType, Operator};
+use expression_engine::{parse, Diagnostic, ExpressionNode, ExpressionNodeType, Operator};
 use implicit_clone::unsync::*;
 use wasm_bindgen::prelude::Closure;
 use wasm_bindgen::{JsCast, UnwrapThrowExt};
@@ -42,25 +42,14 @@ impl Graphing {
             Ok(exp) => {
                 format!("Expression : {}", exp)
             }
-            Err(error) => match error.kind {
-                expression_engine::ErrorKind::Empty => String::from("Empty expression"),
-                expression_engine::ErrorKind::InvalidOpenCloseParantheses => {
-                    String::from("Incorrect number of open/close parantheses")
-                }
-                expression_engine::ErrorKind::InvalidNumberParsed => {
-                    String::from("Failed to parse number(s) in expression")
-                }
-                expression_engine::ErrorKind::InvalidExpression => {
-                    String::from("Expression is invalid")
-                }
-            },
+            Err(error) => Diagnostic::new(&self.expression_text, &error).to_string(),
         }
     }
     pub fn get_exp_res(&self) -> String {
         match parse(&self.expression_text) {
             Ok(exp) => {
                 let var = self.variable_text.parse::<f64>().unwrap();
-                let eval_res = exp.evaluate(&self.variable_text, var);
+                let eval_res = exp.evaluate("x", var);
                 match eval_res {
                     Ok(eval) => {
                         format!("Result : {}", eval)
@@ -68,18 +57,7 @@ impl Graphing {
                     Err(_) => ("".to_string()),
                 }
             }
-            Err(error) => match error.kind {
-                expression_engine::ErrorKind::Empty => String::from("Empty expression"),
-                expression_engine::ErrorKind::InvalidOpenCloseParantheses => {
-                    String::from("Incorrect number of open/close parantheses")
-                }
-                expression_engine::ErrorKind::InvalidNumberParsed => {
-                    String::from("Failed to parse number(s) in expression")
-                }
-                expression_engine::ErrorKind::InvalidExpression => {
-                    String::from("Expression is invalid")
-                }
-            },
+            Err(error) => Diagnostic::new(&self.expression_text, &error).message,
         }
     }
 }
@@ -180,7 +158,7 @@ impl Component for Graphing {
                         </label>
                     </div>
                     <div class="row">
-                        <p class="col-6"> { self.get_exp_str() } </p>
+                        <pre class="col-6"> { self.get_exp_str() } </pre>
                         <p class="col-6"> { self.get_exp_res() } </p>
                     </div>
                 </div>